@@ -0,0 +1,85 @@
+use debruijn::{from_de_bruijn, to_de_bruijn};
+use parser::Expression;
+
+/// Whether `a` and `b` are the same term up to the names of bound variables, like `λx.x` and
+/// `λy.y`. Free variables have to have the same names.
+pub fn alpha_eq(a: &Expression, b: &Expression) -> bool {
+    to_de_bruijn(a) == to_de_bruijn(b)
+}
+
+/// Renames every bound variable after how many lambdas it is inside of: the outermost
+/// binders become `a`, the ones inside of them `b` and so on, going on with `a1` after `z`.
+/// Names that occur free in `expr` are skipped. Terms are alpha-equivalent exactly when their
+/// canonical forms are equal.
+pub fn canonicalize(expr: &Expression) -> Expression {
+    from_de_bruijn(&to_de_bruijn(expr))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{alpha_eq, canonicalize};
+    use parser::test::lex_and_parse;
+    use parser::Expression;
+
+    #[test]
+    fn compare_up_to_bound_names() {
+        let equal = [
+            ("λx.x", "λy.y"),
+            ("λx y.x y", "λa b.a b"),
+            ("λx x.x", "λa b.b"),
+            ("λx.f x", "λy.f y"),
+            ("x (λx.x)", "x (λy.y)"),
+            ("(f a) b", "f a b"),
+            ("λx.((x a) b) c", "λy.y a b c"),
+        ];
+        for &(a, b) in &equal {
+            assert!(
+                alpha_eq(&lex_and_parse(a), &lex_and_parse(b)),
+                "{} {}",
+                a,
+                b
+            );
+        }
+        let different = [
+            ("λx y.x", "λx y.y"),
+            ("λx x.x", "λa b.a"),
+            ("λx.f x", "λx.g x"),
+            ("λx.y", "λy.y"),
+            ("x", "y"),
+            ("f (a b)", "f a b"),
+        ];
+        for &(a, b) in &different {
+            assert!(
+                !alpha_eq(&lex_and_parse(a), &lex_and_parse(b)),
+                "{} {}",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn canonical_names() {
+        let cases = [
+            ("λx y.x y", "λa b.a b"),
+            ("λx.λx.x", "λa b.b"),
+            ("(λx.x) (λy z.z)", "(λa.a) (λa b.b)"),
+            ("λx.a x", "λb.a b"),
+            ("λx y.b x", "λa c.b a"),
+        ];
+        for &(term, expected) in &cases {
+            assert_eq!(canonicalize(&lex_and_parse(term)), lex_and_parse(expected));
+        }
+        let deep = canonicalize(&lex_and_parse(
+            "λa b c d e f g h i j k l m n o p q r s t u v w x y z.λz.z",
+        ));
+        let mut body = &deep;
+        for _ in 0..26 {
+            match body {
+                Expression::Lambda(_, e) => body = e,
+                _ => unreachable!(),
+            }
+        }
+        assert_eq!(body, &lex_and_parse("λa1.a1"));
+    }
+}
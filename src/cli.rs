@@ -0,0 +1,486 @@
+use debugger::Debugger;
+use diagnostics::Renderer;
+use graph::{Limits, ReductionGraph};
+use interpreter::{expand_definitions, interpret, Options, Outcome};
+use lexer::Lexer;
+use parser::{Definition, Expression, Parser};
+use pretty::Format;
+use repl::Repl;
+use span::SpanTree;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use tree::{Diagram, TreeWriter};
+
+/// The program ran and reached a normal form.
+pub const EXIT_SUCCESS: i32 = 0;
+/// The program could not be lexed or parsed, or has no main expression.
+pub const EXIT_PROGRAM_ERROR: i32 = 1;
+/// The command line could not be understood.
+pub const EXIT_USAGE: i32 = 2;
+/// The reduction stopped before reaching a normal form.
+pub const EXIT_NO_NORMAL_FORM: i32 = 3;
+/// The input could not be read.
+pub const EXIT_INPUT_ERROR: i32 = 4;
+
+pub const USAGE: &str = "\
+usage: lamppa run <file> [options]
+       lamppa eval <expression> [options]
+       lamppa debug <file> [options]
+       lamppa graph <file> [options]
+       lamppa tree <file> [options]
+       lamppa repl [options]
+
+Give `-` instead of a file or an expression to read it from standard input.
+
+options:
+    --strategy <normal|applicative|cbn|cbv|head|full>
+                             the order in which redexes are contracted [normal]
+    --steps <n|none>         the most steps to take [10000]
+    --trace <none|steps|redexes>
+                             what to print on the way to the result [redexes]
+    --format <lambda|backslash|debug>
+                             how to print terms [lambda]
+    --lets                   print every `(λx.N) M` as `let x = M in N`, also the ones
+                             that were not written with `let`
+    --depth <n>              how many steps `graph` goes from the start [10]
+    --nodes <n>              the most terms `graph` shows [100]
+    --diagram <dot|mermaid>  the language `tree` writes its diagram in [dot]
+    --binary                 draw every application with two children in `tree`
+    --binders                draw edges from variables to their lambdas in `tree`
+    -h, --help               print this message";
+
+/// What to do, as read from the command line.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Command {
+    /// Runs the program in a file, or in standard input for `-`.
+    Run(String),
+    /// Runs the program given on the command line, or in standard input for `-`.
+    Eval(String),
+    /// Steps through the program in a file, see `Debugger`.
+    Debug(String),
+    /// Writes the reduction graph of the program in a file, or in standard input for `-`, in
+    /// the DOT language.
+    Graph(String, Limits),
+    /// Writes the syntax tree of the program in a file, or in standard input for `-`.
+    Tree(String, TreeWriter),
+    /// Starts an interactive session, see `Repl`.
+    Repl,
+    Help,
+}
+
+/// Reads the arguments after the name of the binary. Options can come before and after the
+/// command.
+pub fn parse_args(args: &[String]) -> Result<(Command, Options), String> {
+    let mut options = Options::default();
+    let mut limits = Limits::default();
+    let mut graph_option = None;
+    let mut diagram = Diagram::default();
+    let (mut binary, mut binders) = (false, false);
+    let mut lets = false;
+    let mut tree_option = None;
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("`{}` needs a value", arg))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok((Command::Help, options)),
+            "--strategy" => options.strategy = value()?.parse()?,
+            "--steps" => options.max_steps = parse_steps(value()?)?,
+            "--trace" => options.trace = value()?.parse()?,
+            "--format" => options.format = value()?.parse()?,
+            "--lets" => lets = true,
+            "--depth" => {
+                limits.depth = parse_number(value()?)?;
+                graph_option = Some(arg);
+            }
+            "--nodes" => {
+                limits.nodes = parse_number(value()?)?;
+                graph_option = Some(arg);
+            }
+            "--diagram" => {
+                diagram = value()?.parse()?;
+                tree_option = Some(arg);
+            }
+            "--binary" => {
+                binary = true;
+                tree_option = Some(arg);
+            }
+            "--binders" => {
+                binders = true;
+                tree_option = Some(arg);
+            }
+            "-" => positional.push(arg),
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+            _ => positional.push(arg),
+        }
+    }
+    if lets {
+        match &mut options.format {
+            Format::Pretty { lets, .. } => *lets = true,
+            Format::Debug => return Err("`--lets` does not work with `--format debug`".into()),
+        }
+    }
+    let command = match positional.as_slice() {
+        [] => return Err("a command is needed".to_string()),
+        [command] if command.as_str() == "repl" => Command::Repl,
+        [command, input] if command.as_str() == "debug" => {
+            if input.as_str() == "-" {
+                return Err(
+                    "`debug` reads its commands from standard input, give it a file".into(),
+                );
+            }
+            Command::Debug(input.to_string())
+        }
+        [command, input] if command.as_str() == "graph" => {
+            Command::Graph(input.to_string(), limits)
+        }
+        [command, input] if command.as_str() == "tree" => {
+            let mut writer = TreeWriter::new(diagram).binary(binary).binders(binders);
+            if let Format::Pretty { lambda_style, .. } = options.format {
+                writer = writer.lambda_style(lambda_style);
+            }
+            Command::Tree(input.to_string(), writer)
+        }
+        [command, input] if command.as_str() == "run" => Command::Run(input.to_string()),
+        [command, input] if command.as_str() == "eval" => Command::Eval(input.to_string()),
+        [command] if command.as_str() == "run" => return Err("`run` needs a file".to_string()),
+        [command] if command.as_str() == "eval" => {
+            return Err("`eval` needs an expression".to_string())
+        }
+        [command, ..] if command.as_str() == "repl" => {
+            return Err("`repl` takes no input".to_string())
+        }
+        [command] if ["debug", "graph", "tree"].contains(&command.as_str()) => {
+            return Err(format!("`{}` needs a file", command))
+        }
+        [command, ..] if ["run", "eval", "debug", "graph", "tree"].contains(&command.as_str()) => {
+            return Err(format!("`{}` takes a single input", command))
+        }
+        [command, ..] => return Err(format!("unknown command `{}`", command)),
+    };
+    if let (Some(option), false) = (graph_option, matches!(command, Command::Graph(_, _))) {
+        return Err(format!("`{}` only works with `graph`", option));
+    }
+    if let (Some(option), false) = (tree_option, matches!(command, Command::Tree(_, _))) {
+        return Err(format!("`{}` only works with `tree`", option));
+    }
+    Ok((command, options))
+}
+
+fn parse_number(n: &str) -> Result<usize, String> {
+    n.parse().map_err(|_| format!("`{}` is not a number", n))
+}
+
+/// Reads a step limit, `none` for no limit.
+pub fn parse_steps(steps: &str) -> Result<Option<usize>, String> {
+    match steps {
+        "none" => Ok(None),
+        n => n
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("`{}` is not a number of steps", n)),
+    }
+}
+
+/// Runs the binary with the arguments after its name and returns the exit code.
+pub fn main(args: &[String]) -> i32 {
+    let (command, options) = match parse_args(args) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+    match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            EXIT_SUCCESS
+        }
+        Command::Run(path) => match read_input(&path) {
+            Ok(source) => run_source(&source, input_name(&path), &options),
+            Err(code) => code,
+        },
+        Command::Eval(ref path) if path == "-" => match read_input(path) {
+            Ok(source) => run_source(&source, input_name(path), &options),
+            Err(code) => code,
+        },
+        Command::Eval(expression) => run_source(&expression, "<expression>", &options),
+        Command::Debug(path) => debug(&path, options),
+        Command::Graph(path, limits) => graph(&path, limits, options.format),
+        Command::Tree(path, writer) => tree(&path, writer),
+        Command::Repl => {
+            let stdin = io::stdin();
+            let stdout = io::stdout();
+            let mut repl = Repl::new(options).colour(stdout.is_terminal());
+            match repl.run(stdin.lock(), stdout.lock()) {
+                Ok(()) => EXIT_SUCCESS,
+                Err(err) => {
+                    eprintln!("error: {}", err);
+                    EXIT_INPUT_ERROR
+                }
+            }
+        }
+    }
+}
+
+fn run_source(source: &str, file_name: &str, options: &Options) -> i32 {
+    with_program(source, file_name, |expression, spans, _| {
+        run(expression, spans, options)
+    })
+}
+
+fn run(expression: Expression, spans: SpanTree, options: &Options) -> i32 {
+    match interpret(expression, spans, options) {
+        Outcome::NormalForm(_, _) => EXIT_SUCCESS,
+        Outcome::OutOfFuel(_, _) | Outcome::Timeout(_, _) | Outcome::Cycle(_, _, _) => {
+            EXIT_NO_NORMAL_FORM
+        }
+    }
+}
+
+/// The text of the file at `path`, or of standard input for `-`. Reports the error and
+/// gives the exit code for it if it cannot be read.
+fn read_input(path: &str) -> Result<String, i32> {
+    let text = if path == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text).map(|_| text)
+    } else {
+        fs::read_to_string(path)
+    };
+    text.map_err(|err| {
+        eprintln!("error: could not read {}: {}", input_name(path), err);
+        EXIT_INPUT_ERROR
+    })
+}
+
+fn input_name(path: &str) -> &str {
+    if path == "-" {
+        "<stdin>"
+    } else {
+        path
+    }
+}
+
+fn graph(path: &str, limits: Limits, format: Format) -> i32 {
+    let text = match read_input(path) {
+        Ok(text) => text,
+        Err(code) => return code,
+    };
+    with_program(&text, input_name(path), |expression, _, _| {
+        let graph = ReductionGraph::explore(expression, limits);
+        print!("{}", graph.to_dot(format));
+        if !graph.is_complete() {
+            eprintln!("warning: the limits left out some of the terms");
+        }
+        EXIT_SUCCESS
+    })
+}
+
+fn tree(path: &str, writer: TreeWriter) -> i32 {
+    let text = match read_input(path) {
+        Ok(text) => text,
+        Err(code) => return code,
+    };
+    with_program(&text, input_name(path), |expression, _, _| {
+        print!("{}", writer.write(&expression));
+        EXIT_SUCCESS
+    })
+}
+
+fn debug(path: &str, options: Options) -> i32 {
+    let text = match read_input(path) {
+        Ok(text) => text,
+        Err(code) => return code,
+    };
+    with_program(&text, path, |expression, spans, definitions| {
+        let stdin = io::stdin();
+        let stdout = io::stdout();
+        let mut debugger = Debugger::new(expression, spans, options)
+            .definitions(definitions)
+            .source(path, &text)
+            .colour(stdout.is_terminal());
+        match debugger.run(stdin.lock(), stdout.lock()) {
+            Ok(()) => EXIT_SUCCESS,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                EXIT_INPUT_ERROR
+            }
+        }
+    })
+}
+
+/// Lexes and parses the program in `text` and gives its main expression, with the
+/// definitions put in, to `action` along with the definitions. Returns the exit code of
+/// `action`, or the one for the errors in the program.
+fn with_program<A>(text: &str, file_name: &str, action: A) -> i32
+where
+    A: FnOnce(Expression, SpanTree, &[Definition]) -> i32,
+{
+    let mut tokens = VecDeque::new();
+    Lexer::new(&mut tokens).run(text.chars().collect::<VecDeque<char>>());
+    let errors = match Parser::new().run_program(tokens) {
+        Ok(program) => {
+            let definitions = program.definitions.clone();
+            match expand_definitions(program) {
+                Some((expression, spans)) => return action(expression, spans, &definitions),
+                None => Vec::new(),
+            }
+        }
+        Err(errors) => errors,
+    };
+    let renderer = Renderer::new(file_name, text).colour(io::stderr().is_terminal());
+    if errors.is_empty() {
+        eprintln!(
+            "{}",
+            renderer.render_message("the program has no main expression", None)
+        );
+    }
+    for error in &errors {
+        eprintln!("{}", renderer.render(error));
+    }
+    EXIT_PROGRAM_ERROR
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        main, parse_args, run_source, Command, EXIT_INPUT_ERROR, EXIT_NO_NORMAL_FORM,
+        EXIT_PROGRAM_ERROR, EXIT_SUCCESS,
+    };
+    use graph::Limits;
+    use interpreter::{Options, Strategy, Trace};
+    use pretty::{Format, LambdaStyle};
+    use std::env;
+    use std::fs;
+    use std::process;
+    use tree::{Diagram, TreeWriter};
+
+    fn args(string_slice: &str) -> Vec<String> {
+        string_slice.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parse_commands() {
+        let (command, options) = parse_args(&args("run prelude.lc")).unwrap();
+        assert_eq!(command, Command::Run("prelude.lc".to_string()));
+        assert_eq!(options, Options::default());
+        let (command, _) = parse_args(&args("eval -")).unwrap();
+        assert_eq!(command, Command::Eval("-".to_string()));
+        let (command, _) = parse_args(&args("repl --steps 100")).unwrap();
+        assert_eq!(command, Command::Repl);
+        let (command, _) = parse_args(&args("graph --depth 3 ex.lc --nodes 20")).unwrap();
+        let limits = Limits {
+            depth: 3,
+            nodes: 20,
+        };
+        assert_eq!(command, Command::Graph("ex.lc".to_string(), limits));
+        let (command, _) = parse_args(&args(
+            "tree --diagram mermaid a.lc --binders --format backslash",
+        ))
+        .unwrap();
+        let writer = TreeWriter::new(Diagram::Mermaid)
+            .binders(true)
+            .lambda_style(LambdaStyle::Backslash);
+        assert_eq!(command, Command::Tree("a.lc".to_string(), writer));
+        let (command, _) = parse_args(&args("debug omega.lc")).unwrap();
+        assert_eq!(command, Command::Debug("omega.lc".to_string()));
+        let (command, _) = parse_args(&args("run a.lc --help")).unwrap();
+        assert_eq!(command, Command::Help);
+    }
+
+    #[test]
+    fn parse_options() {
+        let (_, options) = parse_args(&args(
+            "--strategy cbv eval x --steps 5 --trace none --format backslash",
+        ))
+        .unwrap();
+        assert_eq!(options.strategy, Strategy::CallByValue);
+        assert_eq!(options.max_steps, Some(5));
+        assert_eq!(options.trace, Trace::None);
+        assert_eq!(
+            options.format,
+            Format::Pretty {
+                lambda_style: LambdaStyle::Backslash,
+                lets: false,
+            }
+        );
+        let (_, options) = parse_args(&args("eval x --steps none --format debug")).unwrap();
+        assert_eq!(options.max_steps, None);
+        assert_eq!(options.format, Format::Debug);
+        let (_, options) = parse_args(&args("--lets eval x --format backslash")).unwrap();
+        assert_eq!(
+            options.format,
+            Format::Pretty {
+                lambda_style: LambdaStyle::Backslash,
+                lets: true,
+            }
+        );
+    }
+
+    #[test]
+    fn reject_bad_arguments() {
+        for &line in &[
+            "",
+            "run",
+            "eval a b",
+            "repl a",
+            "debug",
+            "debug -",
+            "graph",
+            "graph a.lc --depth deep",
+            "run a.lc --nodes 5",
+            "tree a.lc --diagram svg",
+            "graph a.lc --binary",
+            "reduce x",
+            "eval x --steps",
+            "eval x --steps many",
+            "eval x --strategy lazy",
+            "eval x --trace all",
+            "eval x --format latex",
+            "eval x --format debug --lets",
+            "eval x --verbose",
+        ] {
+            assert!(parse_args(&args(line)).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn exit_codes() {
+        let options = Options {
+            max_steps: Some(10),
+            trace: Trace::None,
+            ..Options::default()
+        };
+        assert_eq!(run_source("(λx.x) y", "<test>", &options), EXIT_SUCCESS);
+        assert_eq!(
+            run_source("I = λx.x; I I", "<test>", &options),
+            EXIT_SUCCESS
+        );
+        assert_eq!(
+            run_source("λx.x) y", "<test>", &options),
+            EXIT_PROGRAM_ERROR
+        );
+        assert_eq!(run_source("x = y;", "<test>", &options), EXIT_PROGRAM_ERROR);
+        assert_eq!(
+            run_source("(λx.x x) (λx.x x)", "<test>", &options),
+            EXIT_NO_NORMAL_FORM
+        );
+    }
+
+    #[test]
+    fn report_unreadable_input() {
+        let directory = env::temp_dir();
+        let run = format!("run {}", directory.display());
+        assert_eq!(main(&args(&run)), EXIT_INPUT_ERROR);
+        let path = directory.join(format!("lamppa-cli-test-{}.lc", process::id()));
+        fs::write(&path, b"\xff\xfe").unwrap();
+        let run = format!("run {}", path.display());
+        assert_eq!(main(&args(&run)), EXIT_INPUT_ERROR);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(main(&args(&run)), EXIT_INPUT_ERROR);
+    }
+}
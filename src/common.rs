@@ -1,4 +1,3 @@
-use file::CharFile;
 use std::collections::VecDeque;
 use std::ops::Deref;
 use std::sync::mpsc::Receiver;
@@ -28,17 +27,15 @@ impl<T> Source<T> for Vec<T> {
     }
 }
 
-//TODO: figure out an implementation an efficient implementation where you don't have to reverse the string beforehand.
+// TODO: taking from the front of a `String` moves the rest of it, so long inputs are better
+// off in a `VecDeque<char>`.
 impl Source<char> for String {
-    // fn take(&mut self) -> Option<char> {
-    //     if self.is_empty() {
-    //         None
-    //     } else {
-    //         Some(self.remove(0))
-    //     }
-    // }
     fn take(&mut self) -> Option<char> {
-        self.pop()
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.remove(0))
+        }
     }
 }
 
@@ -54,7 +51,6 @@ impl<T> Source<T> for Receiver<T> {
     }
 }
 
-
 /// ´Sink´s are things take take in some type T. Generaly Sinks are used in tandem
 /// with sources so that if something is put into a sink it should appear in a source somewhere.
 pub trait Sink<T> {
@@ -73,7 +69,7 @@ impl<T> Sink<T> for VecDeque<T> {
     }
 }
 
-impl<'a, T> Sink<T> for &'a mut VecDeque<T> {
+impl<T> Sink<T> for &mut VecDeque<T> {
     fn put(&mut self, thing: T) {
         self.push_back(thing);
     }
@@ -0,0 +1,265 @@
+use interpreter::{merge_application, Shape, Term};
+use parser::Expression;
+use std::collections::HashSet;
+use std::mem;
+
+/// A lambda term without names for bound variables. `Var(0)` is bound by the nearest lambda
+/// around it, `Var(1)` by the one around that and so on. Free variables keep their names.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum DbExpr {
+    Var(usize),
+    Free(String),
+    Lambda(Box<DbExpr>),
+    Application(Vec<DbExpr>),
+}
+
+impl Term for DbExpr {
+    fn shape(&self) -> Shape<'_, Self> {
+        match self {
+            DbExpr::Var(_) | DbExpr::Free(_) => Shape::Variable,
+            DbExpr::Lambda(e) => Shape::Lambda(e),
+            DbExpr::Application(v) => Shape::Application(v),
+        }
+    }
+
+    fn is_eta_redex(&self) -> bool {
+        match self {
+            DbExpr::Lambda(e) => match &**e {
+                DbExpr::Application(v) => match v.split_last() {
+                    Some((DbExpr::Var(0), function)) => {
+                        !function.is_empty() && !function.iter().any(|e| e.has_var(0))
+                    }
+                    _ => false,
+                },
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Converts `expr`, merging every application in function position into the application
+/// around it, so that `(f a) b` and `f a b` give the same term.
+pub fn to_de_bruijn(expr: &Expression) -> DbExpr {
+    with_binders(expr, &mut Vec::new())
+}
+
+fn with_binders<'a>(expr: &'a Expression, binders: &mut Vec<&'a str>) -> DbExpr {
+    match expr {
+        Expression::Variable(i) => match binders.iter().rev().position(|b| b == i) {
+            Some(index) => DbExpr::Var(index),
+            None => DbExpr::Free(i.clone()),
+        },
+        Expression::Lambda(i, e) => {
+            binders.push(i);
+            let body = with_binders(e, binders);
+            binders.pop();
+            DbExpr::Lambda(Box::new(body))
+        }
+        Expression::Application(v) => {
+            application(v.iter().map(|e| with_binders(e, binders)).collect())
+        }
+    }
+}
+
+/// Names the bound variables after how many lambdas they are inside of: the outermost
+/// binders become `a`, the ones inside of them `b` and so on, going on with `a1` after `z`.
+/// Names of free variables are skipped.
+pub fn from_de_bruijn(expr: &DbExpr) -> Expression {
+    let free = expr.free_vars();
+    let mut names = (0..)
+        .map(|n: usize| {
+            let letter = (b'a' + (n % 26) as u8) as char;
+            match n / 26 {
+                0 => letter.to_string(),
+                round => format!("{}{}", letter, round),
+            }
+        })
+        .filter(|name| !free.contains(name));
+    with_names(expr, 0, &mut Vec::new(), &mut names)
+}
+
+/// `depth_names` are the names of the lambdas at each depth so far, new depths take the next
+/// name from `names`.
+fn with_names<I>(
+    expr: &DbExpr,
+    depth: usize,
+    depth_names: &mut Vec<String>,
+    names: &mut I,
+) -> Expression
+where
+    I: Iterator<Item = String>,
+{
+    match expr {
+        DbExpr::Var(index) => Expression::Variable(depth_names[depth - 1 - index].clone()),
+        DbExpr::Free(i) => Expression::Variable(i.clone()),
+        DbExpr::Lambda(e) => {
+            if depth_names.len() == depth {
+                depth_names.push(names.next().unwrap());
+            }
+            let body = with_names(e, depth + 1, depth_names, names);
+            Expression::Lambda(depth_names[depth].clone(), Box::new(body))
+        }
+        DbExpr::Application(v) => Expression::Application(
+            v.iter()
+                .map(|e| with_names(e, depth, depth_names, names))
+                .collect(),
+        ),
+    }
+}
+
+impl DbExpr {
+    pub fn free_vars(&self) -> HashSet<String> {
+        match self {
+            DbExpr::Var(_) => HashSet::new(),
+            DbExpr::Free(i) => {
+                let mut vars = HashSet::new();
+                vars.insert(i.clone());
+                vars
+            }
+            DbExpr::Lambda(e) => e.free_vars(),
+            DbExpr::Application(v) => v.iter().flat_map(DbExpr::free_vars).collect(),
+        }
+    }
+
+    /// Whether the variable that is `Var(index)` at the top of `self` occurs in it.
+    fn has_var(&self, index: usize) -> bool {
+        match self {
+            DbExpr::Var(i) => *i == index,
+            DbExpr::Free(_) => false,
+            DbExpr::Lambda(e) => e.has_var(index + 1),
+            DbExpr::Application(v) => v.iter().any(|e| e.has_var(index)),
+        }
+    }
+
+    /// Takes one off the variables that are bound outside of the `cutoff` innermost lambdas,
+    /// for when a lambda around `self` that binds none of them is removed.
+    fn shift_down(&self, cutoff: usize) -> DbExpr {
+        match self {
+            DbExpr::Var(index) if *index > cutoff => DbExpr::Var(index - 1),
+            DbExpr::Var(_) | DbExpr::Free(_) => self.clone(),
+            DbExpr::Lambda(e) => DbExpr::Lambda(Box::new(e.shift_down(cutoff + 1))),
+            DbExpr::Application(v) => {
+                DbExpr::Application(v.iter().map(|e| e.shift_down(cutoff)).collect())
+            }
+        }
+    }
+
+    /// Adds `d` to the variables that are bound outside of the `cutoff` innermost lambdas,
+    /// for when the term is moved under `d` more lambdas.
+    fn shift(&self, d: usize, cutoff: usize) -> DbExpr {
+        match self {
+            DbExpr::Var(index) if *index >= cutoff => DbExpr::Var(index + d),
+            DbExpr::Var(_) | DbExpr::Free(_) => self.clone(),
+            DbExpr::Lambda(e) => DbExpr::Lambda(Box::new(e.shift(d, cutoff + 1))),
+            DbExpr::Application(v) => {
+                DbExpr::Application(v.iter().map(|e| e.shift(d, cutoff)).collect())
+            }
+        }
+    }
+
+    /// Substitutes `arg` in the body `self` of a lambda for the variable it binds, which is
+    /// `Var(depth)` inside of `depth` more lambdas. The variables bound outside of the lambda
+    /// lose it as a binder, so they are shifted down by one.
+    fn instantiate(&self, depth: usize, arg: &DbExpr) -> DbExpr {
+        match self {
+            DbExpr::Var(index) if *index == depth => arg.shift(depth, 0),
+            DbExpr::Var(index) if *index > depth => DbExpr::Var(index - 1),
+            DbExpr::Var(_) | DbExpr::Free(_) => self.clone(),
+            DbExpr::Lambda(e) => DbExpr::Lambda(Box::new(e.instantiate(depth + 1, arg))),
+            DbExpr::Application(v) => {
+                DbExpr::Application(v.iter().map(|e| e.instantiate(depth, arg)).collect())
+            }
+        }
+    }
+
+    /// Contracts the beta or eta redex at `path`, see `SpanTree::get` for how paths work. Like
+    /// the named interpreter, this keeps applications flat.
+    pub fn contract_at(self, path: &[usize]) -> DbExpr {
+        match (self, path.split_first()) {
+            (DbExpr::Application(mut v), None) => {
+                let body = match v.remove(0) {
+                    DbExpr::Lambda(body) => body,
+                    _ => panic!("there is no redex at {:?}", path),
+                };
+                let arg = v.remove(0);
+                v.insert(0, body.instantiate(0, &arg).flatten());
+                application(v)
+            }
+            (DbExpr::Lambda(e), None) => match *e {
+                DbExpr::Application(mut v) => {
+                    v.pop();
+                    application(v.iter().map(|e| e.shift_down(0)).collect())
+                }
+                _ => panic!("there is no redex at {:?}", path),
+            },
+            (DbExpr::Lambda(e), Some((0, rest))) => DbExpr::Lambda(Box::new(e.contract_at(rest))),
+            (DbExpr::Application(mut v), Some((&i, rest))) => {
+                let e = mem::replace(&mut v[i], DbExpr::Var(0));
+                v[i] = e.contract_at(rest);
+                DbExpr::Application(v)
+            }
+            _ => panic!("there is no redex at {:?}", path),
+        }
+    }
+
+    fn flatten(self) -> DbExpr {
+        match self {
+            DbExpr::Lambda(e) => DbExpr::Lambda(Box::new(e.flatten())),
+            DbExpr::Application(v) => application(v.into_iter().map(DbExpr::flatten).collect()),
+            _ => self,
+        }
+    }
+}
+
+fn application(v: Vec<DbExpr>) -> DbExpr {
+    merge_application(
+        v,
+        |e| match e {
+            DbExpr::Application(v) => Ok(v),
+            e => Err(e),
+        },
+        DbExpr::Application,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_de_bruijn, to_de_bruijn, DbExpr};
+    use alpha::alpha_eq;
+    use parser::test::lex_and_parse;
+
+    #[test]
+    fn convert_to_de_bruijn() {
+        let expected = DbExpr::Lambda(Box::new(DbExpr::Lambda(Box::new(DbExpr::Application(
+            vec![DbExpr::Var(1), DbExpr::Var(0), DbExpr::Free("z".into())],
+        )))));
+        assert_eq!(to_de_bruijn(&lex_and_parse("λx y.x y z")), expected);
+        let expected = DbExpr::Lambda(Box::new(DbExpr::Lambda(Box::new(DbExpr::Var(0)))));
+        assert_eq!(to_de_bruijn(&lex_and_parse("λx x.x")), expected);
+    }
+
+    #[test]
+    fn convert_both_ways() {
+        for &term in &["λx y.x y z", "λx.λx.x", "(λx.x) (λy.a y)", "λa.b (λc.a c)"] {
+            let expression = lex_and_parse(term);
+            let converted = from_de_bruijn(&to_de_bruijn(&expression));
+            assert!(alpha_eq(&converted, &expression), "{}", term);
+        }
+        let expression = from_de_bruijn(&to_de_bruijn(&lex_and_parse("λx.a x")));
+        assert_eq!(expression, lex_and_parse("λb.a b"));
+    }
+
+    #[test]
+    fn contract_with_shifting() {
+        let term = to_de_bruijn(&lex_and_parse("λa b.(λx y.x b) a"));
+        let expected = to_de_bruijn(&lex_and_parse("λa b y.a b"));
+        assert_eq!(term.contract_at(&[0, 0]), expected);
+        let term = to_de_bruijn(&lex_and_parse("λu.(λx y.x) (λv.u v)"));
+        let expected = to_de_bruijn(&lex_and_parse("λu y v.u v"));
+        assert_eq!(term.contract_at(&[0]), expected);
+        let term = to_de_bruijn(&lex_and_parse("(λx.x b) (f a) c"));
+        let expected = to_de_bruijn(&lex_and_parse("f a b c"));
+        assert_eq!(term.contract_at(&[]), expected);
+    }
+}
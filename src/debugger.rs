@@ -0,0 +1,502 @@
+use diagnostics::Renderer;
+use interpreter::{contract_with_spans, next_redexes, redexes, subterm, Options, StepKind};
+use lexer::Position;
+use parser::{Definition, Expression};
+use span::{span_inside, SpanTree};
+use std::io::{self, BufRead, Write};
+
+const PROMPT: &str = "(debug) ";
+
+pub const HELP: &str = "\
+    s, step          take the step the strategy would take
+    c, contract N    contract redex number N
+    u, undo          go back one step
+    b, break NAME    stop before contracting a redex whose lambda is the definition NAME
+                     or binds NAME
+    d, delete NAME   remove a breakpoint
+    r, continue      step until a breakpoint, the normal form or the step limit
+    l, list          show the term and its redexes again
+    h, help          show this message
+    q, quit          leave";
+
+/// A redex in the current term of a `Debugger`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Redex {
+    /// See `SpanTree::get` for how paths work.
+    pub path: Vec<usize>,
+    pub kind: StepKind,
+    /// The variable the lambda of the redex binds. Breakpoints are set on these.
+    pub binder: String,
+    /// The definition the lambda of the redex is, if it is one of the definitions given to
+    /// `Debugger::definitions`. Breakpoints are set on these too.
+    pub definition: Option<String>,
+    /// The redex on its own, without the rest of an application it starts.
+    pub expression: Expression,
+    /// Where the redex is in the source, if it is known.
+    pub position: Option<Position>,
+}
+
+/// Why `Debugger::resume` stopped.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Stop {
+    NormalForm,
+    /// The next step contracts this redex, which has a breakpoint on it.
+    Breakpoint(Redex),
+    /// `Options::max_steps` steps were taken.
+    OutOfFuel,
+}
+
+/// Reduces a term one step at a time, letting the user pick the redexes and go back.
+pub struct Debugger {
+    /// Every term so far with the source positions of its parts, the current one last.
+    history: Vec<(Expression, SpanTree)>,
+    options: Options,
+    breakpoints: Vec<String>,
+    /// The names of the definitions the term was expanded from, with the positions of their
+    /// expressions.
+    definitions: Vec<(String, Position)>,
+    /// The path to the redex `resume` last stopped at a breakpoint before, as long as the
+    /// term has not changed since.
+    stopped_at: Option<Vec<usize>>,
+    /// The name and text of the source, for pointing at redexes in it.
+    source: Option<(String, String)>,
+    colour: bool,
+}
+
+impl Debugger {
+    pub fn new(expr: Expression, spans: SpanTree, options: Options) -> Self {
+        Debugger {
+            history: vec![(expr, spans)],
+            options,
+            breakpoints: Vec::new(),
+            definitions: Vec::new(),
+            stopped_at: None,
+            source: None,
+            colour: false,
+        }
+    }
+
+    /// The file name and text the term was parsed from, to show redexes in.
+    pub fn source(mut self, file_name: &str, text: &str) -> Self {
+        self.source = Some((file_name.to_string(), text.to_string()));
+        self
+    }
+
+    /// The definitions the term was expanded from, so that breakpoints can be set on their
+    /// names. A redex starts with a definition when its lambda came from the source of that
+    /// definition's expression, which substitution and reduction leave as it is.
+    pub fn definitions(mut self, definitions: &[Definition]) -> Self {
+        self.definitions = definitions
+            .iter()
+            .filter_map(|d| d.spans.position.map(|p| (d.name.clone(), p)))
+            .collect();
+        self
+    }
+
+    /// Turns ANSI colours on or off. They are off by default.
+    pub fn colour(mut self, colour: bool) -> Self {
+        self.colour = colour;
+        self
+    }
+
+    pub fn current(&self) -> &Expression {
+        &self.history[self.history.len() - 1].0
+    }
+
+    fn current_spans(&self) -> &SpanTree {
+        &self.history[self.history.len() - 1].1
+    }
+
+    /// How many steps were taken to get to the current term.
+    pub fn steps(&self) -> usize {
+        self.history.len() - 1
+    }
+
+    /// The beta redexes of the current term and its eta redexes if `Options::eta` is set,
+    /// outer ones before the ones inside of them.
+    pub fn redexes(&self) -> Vec<Redex> {
+        let mut kinds = vec![StepKind::Beta];
+        if self.options.eta {
+            kinds.push(StepKind::Eta);
+        }
+        kinds
+            .into_iter()
+            .flat_map(|kind| {
+                redexes(self.current(), kind)
+                    .into_iter()
+                    .map(move |path| (kind, path))
+            })
+            .map(|(kind, path)| self.redex(kind, path))
+            .collect()
+    }
+
+    fn redex(&self, kind: StepKind, path: Vec<usize>) -> Redex {
+        let expr = subterm(self.current(), &path).unwrap();
+        let spans = self.current_spans().get(&path);
+        let (binder, expression, position) = match (kind, expr) {
+            (StepKind::Beta, Expression::Application(v)) => (
+                &v[0],
+                Expression::Application(v[..2].to_vec()),
+                spans.and_then(|s| {
+                    span_inside(s.position, s.children[0].position, s.children[1].position)
+                }),
+            ),
+            _ => (expr, expr.clone(), spans.and_then(|s| s.position)),
+        };
+        let binder = match binder {
+            Expression::Lambda(i, _) => i.clone(),
+            _ => unreachable!(),
+        };
+        let lambda_position = match kind {
+            StepKind::Beta => spans.and_then(|s| s.children[0].position),
+            StepKind::Eta => spans.and_then(|s| s.position),
+        };
+        let definition = lambda_position.and_then(|p| {
+            self.definitions
+                .iter()
+                .rev()
+                .find(|&&(_, q)| q == p)
+                .map(|(name, _)| name.clone())
+        });
+        Redex {
+            path,
+            kind,
+            binder,
+            definition,
+            expression,
+            position,
+        }
+    }
+
+    /// The paths to the redexes the strategy contracts next, `None` in a normal form.
+    pub fn next(&self) -> Option<(StepKind, Vec<Vec<usize>>)> {
+        next_redexes(self.current(), &self.options)
+    }
+
+    /// Takes the step the strategy would take. Returns whether there was one.
+    pub fn step(&mut self) -> bool {
+        match self.next() {
+            Some((_, paths)) => {
+                self.contract_paths(paths);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Contracts the redex with the given index in `redexes`.
+    pub fn contract(&mut self, index: usize) -> Result<(), String> {
+        let redexes = self.redexes();
+        match redexes.get(index) {
+            Some(redex) => {
+                self.contract_paths(vec![redex.path.clone()]);
+                Ok(())
+            }
+            None if redexes.is_empty() => Err("there are no redexes".to_string()),
+            None => Err(format!(
+                "there is no redex {}, they go from 1 to {}",
+                index + 1,
+                redexes.len()
+            )),
+        }
+    }
+
+    fn contract_paths(&mut self, paths: Vec<Vec<usize>>) {
+        let (expr, spans) = self.history[self.history.len() - 1].clone();
+        let (expr, spans, _) = contract_with_spans(expr, spans, paths);
+        self.history.push((expr, spans));
+        self.stopped_at = None;
+    }
+
+    /// Goes back one step. Returns whether there was one to go back.
+    pub fn undo(&mut self) -> bool {
+        if self.history.len() > 1 {
+            self.history.pop();
+            self.stopped_at = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Stops before contracting a redex whose lambda is the definition `name` or binds `name`.
+    pub fn add_breakpoint(&mut self, name: &str) {
+        if !self.breakpoints.iter().any(|b| b == name) {
+            self.breakpoints.push(name.to_string());
+        }
+    }
+
+    /// The breakpoint on `redex`, if it has one.
+    fn breakpoint(&self, redex: &Redex) -> Option<&String> {
+        self.breakpoints
+            .iter()
+            .find(|&b| *b == redex.binder || redex.definition.as_ref() == Some(b))
+    }
+
+    /// Returns whether there was such a breakpoint.
+    pub fn remove_breakpoint(&mut self, name: &str) -> bool {
+        let count = self.breakpoints.len();
+        self.breakpoints.retain(|b| b != name);
+        self.breakpoints.len() < count
+    }
+
+    /// Takes steps like `step` until the normal form, until the next step would contract a
+    /// redex with a breakpoint or until `Options::max_steps` steps were taken. The breakpoint
+    /// the last call stopped at is passed over, so that resuming there moves on.
+    pub fn resume(&mut self) -> Stop {
+        let mut steps = 0;
+        loop {
+            let (kind, paths) = match self.next() {
+                Some(next) => next,
+                None => return Stop::NormalForm,
+            };
+            let breakpoint = paths
+                .iter()
+                .filter(|&path| self.stopped_at.as_ref() != Some(path))
+                .map(|path| self.redex(kind, path.clone()))
+                .find(|redex| self.breakpoint(redex).is_some());
+            if let Some(redex) = breakpoint {
+                self.stopped_at = Some(redex.path.clone());
+                return Stop::Breakpoint(redex);
+            }
+            if self.options.max_steps.is_some_and(|max| steps >= max) {
+                return Stop::OutOfFuel;
+            }
+            self.contract_paths(paths);
+            steps += 1;
+        }
+    }
+
+    /// Reads commands from `input` and writes the term and its redexes to `output` after
+    /// every one, until the input ends or `quit` is given.
+    pub fn run<R, W>(&mut self, input: R, mut output: W) -> io::Result<()>
+    where
+        R: BufRead,
+        W: Write,
+    {
+        write!(output, "{}{}", self.show(), PROMPT)?;
+        output.flush()?;
+        for line in input.lines() {
+            let line = line?;
+            let mut words = line.split_whitespace();
+            let command = words.next().unwrap_or("");
+            let argument = words.next();
+            let reply = match (command, argument) {
+                ("", _) => String::new(),
+                ("q", _) | ("quit", _) => return Ok(()),
+                ("h", _) | ("help", _) => format!("{}\n", HELP),
+                ("l", _) | ("list", _) => self.show(),
+                ("s", _) | ("step", _) => {
+                    if self.step() {
+                        self.show()
+                    } else {
+                        "already in normal form\n".to_string()
+                    }
+                }
+                ("c", Some(n)) | ("contract", Some(n)) => {
+                    match n.parse::<usize>().ok().filter(|&n| n > 0) {
+                        Some(n) => match self.contract(n - 1) {
+                            Ok(()) => self.show(),
+                            Err(message) => format!("{}\n", message),
+                        },
+                        None => format!("`{}` is not the number of a redex\n", n),
+                    }
+                }
+                ("u", _) | ("undo", _) => {
+                    if self.undo() {
+                        self.show()
+                    } else {
+                        "nothing to undo\n".to_string()
+                    }
+                }
+                ("b", Some(name)) | ("break", Some(name)) => {
+                    self.add_breakpoint(name);
+                    String::new()
+                }
+                ("d", Some(name)) | ("delete", Some(name)) => {
+                    if self.remove_breakpoint(name) {
+                        String::new()
+                    } else {
+                        format!("there is no breakpoint on {}\n", name)
+                    }
+                }
+                ("r", _) | ("continue", _) => {
+                    let reason = match self.resume() {
+                        Stop::NormalForm => "reached the normal form".to_string(),
+                        Stop::Breakpoint(redex) => {
+                            format!("breakpoint on {}", self.breakpoint(&redex).unwrap())
+                        }
+                        Stop::OutOfFuel => "stopped at the step limit".to_string(),
+                    };
+                    format!("{}\n{}", reason, self.show())
+                }
+                _ => format!("cannot do `{}`, see help\n", line.trim()),
+            };
+            write!(output, "{}{}", reply, PROMPT)?;
+            output.flush()?;
+        }
+        writeln!(output)
+    }
+
+    /// The current term with its numbered redexes, the ones the strategy contracts next
+    /// marked with `>`, and the first of those pointed out in the source if it is known.
+    fn show(&self) -> String {
+        let format = self.options.format;
+        let mut out = format!("{}: {}\n", self.steps(), format.show(self.current()));
+        let next = self.next().map(|(_, paths)| paths).unwrap_or_default();
+        let redexes = self.redexes();
+        if redexes.is_empty() {
+            out.push_str("no redexes, this is a normal form\n");
+        }
+        for (i, redex) in redexes.iter().enumerate() {
+            let marker = if next.contains(&redex.path) { '>' } else { ' ' };
+            out.push_str(&format!(
+                "{} {} {} {}",
+                marker,
+                i + 1,
+                redex.kind,
+                format.show(&redex.expression)
+            ));
+            if let Some(position) = redex.position {
+                out.push_str(&format!(" at {}", position));
+            }
+            out.push('\n');
+        }
+        let first_next = redexes.iter().find(|redex| next.contains(&redex.path));
+        if let (Some((file_name, text)), Some(redex)) = (&self.source, first_next) {
+            if redex.position.is_some() {
+                let renderer = Renderer::new(file_name, text).colour(self.colour);
+                out.push_str(&renderer.render_note("contracted next", redex.position));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Debugger, Stop};
+    use interpreter::{expand_definitions, Options, Strategy};
+    use parser::test::lex_and_parse_program;
+
+    fn debugger(source: &str, options: Options) -> Debugger {
+        let program = lex_and_parse_program(source);
+        let definitions = program.definitions.clone();
+        let (expression, spans) = expand_definitions(program).unwrap();
+        Debugger::new(expression, spans, options)
+            .definitions(&definitions)
+            .source("test.lc", source)
+    }
+
+    #[test]
+    fn choose_redexes_and_undo() {
+        let mut debugger = debugger("(λx.x x) ((λy.y) z)", Options::default());
+        let redexes = debugger.redexes();
+        assert_eq!(redexes.len(), 2);
+        assert_eq!(redexes[0].binder, "x");
+        assert_eq!(redexes[1].expression.to_string(), "(λy.y) z");
+        assert_eq!(redexes[1].position.unwrap().to_string(), "1:12-1:18");
+        debugger.contract(1).unwrap();
+        assert_eq!(debugger.current().to_string(), "(λx.x x) z");
+        assert!(debugger.contract(1).is_err());
+        assert!(debugger.undo());
+        assert!(debugger.step());
+        assert_eq!(debugger.current().to_string(), "(λy.y) z ((λy.y) z)");
+        assert_eq!(debugger.steps(), 1);
+        assert!(debugger.undo());
+        assert!(!debugger.undo());
+    }
+
+    #[test]
+    fn stop_at_breakpoints() {
+        let source = "I = λi.i; K = λk l.k; K (I a) (I b)";
+        let mut debugger = debugger(source, Options::default());
+        debugger.add_breakpoint("i");
+        match debugger.resume() {
+            Stop::Breakpoint(redex) => assert_eq!(redex.expression.to_string(), "(λi.i) a"),
+            stop => panic!("stopped with {:?}", stop),
+        }
+        assert_eq!(debugger.current().to_string(), "(λi.i) a");
+        assert_eq!(debugger.steps(), 2);
+        assert!(debugger.remove_breakpoint("i"));
+        assert_eq!(debugger.resume(), Stop::NormalForm);
+        assert_eq!(debugger.current().to_string(), "a");
+        assert!(debugger.undo() && debugger.undo() && debugger.undo());
+        debugger.add_breakpoint("I");
+        match debugger.resume() {
+            Stop::Breakpoint(redex) => {
+                assert_eq!(redex.definition, Some("I".to_string()));
+                assert_eq!(redex.binder, "i");
+            }
+            stop => panic!("stopped with {:?}", stop),
+        }
+        assert_eq!(debugger.steps(), 2);
+        assert!(debugger.undo() && debugger.undo());
+        debugger.add_breakpoint("K");
+        match debugger.resume() {
+            Stop::Breakpoint(redex) => assert_eq!(redex.definition, Some("K".to_string())),
+            stop => panic!("stopped with {:?}", stop),
+        }
+        assert_eq!(debugger.steps(), 0);
+    }
+
+    #[test]
+    fn stop_before_the_first_step() {
+        let mut nested = debugger("(λi.i) ((λk.k) a)", Options::default());
+        nested.add_breakpoint("i");
+        match nested.resume() {
+            Stop::Breakpoint(redex) => assert!(redex.path.is_empty()),
+            stop => panic!("stopped with {:?}", stop),
+        }
+        assert_eq!(nested.steps(), 0);
+        assert_eq!(nested.resume(), Stop::NormalForm);
+        assert_eq!(nested.steps(), 2);
+        let mut twice = debugger("(λi.i) (λi.i) a", Options::default());
+        twice.add_breakpoint("i");
+        assert!(matches!(twice.resume(), Stop::Breakpoint(_)));
+        assert!(matches!(twice.resume(), Stop::Breakpoint(_)));
+        assert_eq!(twice.steps(), 1);
+    }
+
+    #[test]
+    fn stop_at_the_step_limit() {
+        let options = Options {
+            strategy: Strategy::CallByValue,
+            max_steps: Some(3),
+            ..Options::default()
+        };
+        let mut debugger = debugger("(λx.x x) (λx.x x)", options);
+        assert_eq!(debugger.resume(), Stop::OutOfFuel);
+        assert_eq!(debugger.steps(), 3);
+    }
+
+    #[test]
+    fn run_commands() {
+        let mut debugger = debugger("(λx.x) y", Options::default());
+        let mut output = Vec::new();
+        debugger
+            .run("c 2\nstep\nstep\nu\nq\n".as_bytes(), &mut output)
+            .unwrap();
+        let expected = "\
+0: (λx.x) y
+> 1 β (λx.x) y at 1:2-1:8
+note: contracted next
+ --> test.lc:1:2
+  |
+1 | (λx.x) y
+  |  ^^^^^^^
+(debug) there is no redex 2, they go from 1 to 1
+(debug) 1: y
+no redexes, this is a normal form
+(debug) already in normal form
+(debug) 0: (λx.x) y
+> 1 β (λx.x) y at 1:2-1:8
+note: contracted next
+ --> test.lc:1:2
+  |
+1 | (λx.x) y
+  |  ^^^^^^^
+(debug) ";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
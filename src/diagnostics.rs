@@ -0,0 +1,323 @@
+use lexer::{LexError, Position};
+use parser::ParseError;
+use std::fmt;
+
+const RED: &str = "\u{1b}[1;31m";
+const GREEN: &str = "\u{1b}[1;32m";
+const BLUE: &str = "\u{1b}[1;34m";
+const BOLD: &str = "\u{1b}[1m";
+const RESET: &str = "\u{1b}[0m";
+
+/// Anything that can be reported to the user as an error pointing into the source text.
+pub trait Diagnostic: fmt::Display {
+    /// Where in the source the problem is, if it can be pinned down to a place.
+    fn position(&self) -> Option<Position>;
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexError::IdentifierError(message, _) => write!(fmt, "{}", message),
+            LexError::InvalidCharacterError(c, _) => write!(fmt, "invalid character `{}`", c),
+            LexError::UnterminatedCommentError(_) => {
+                write!(fmt, "this block comment is never closed")
+            }
+        }
+    }
+}
+
+impl Diagnostic for LexError {
+    fn position(&self) -> Option<Position> {
+        match self {
+            LexError::IdentifierError(_, position) => Some(*position),
+            LexError::InvalidCharacterError(_, position) => Some(*position),
+            LexError::UnterminatedCommentError(position) => Some(*position),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedClosingBracket(_) => write!(fmt, "unexpected closing bracket"),
+            ParseError::DotWithoutLambda(_) => write!(fmt, "a dot was found without a lambda"),
+            ParseError::MissingIdentifierAfterLambda(_) => {
+                write!(fmt, "an identifier was expected after a lambda")
+            }
+            ParseError::MissingDot(_) => write!(
+                fmt,
+                "a dot or an identifier was expected after a lambda and an identifier"
+            ),
+            ParseError::EmptyParenthesis(_) => {
+                write!(fmt, "an expression in parenthesis was empty")
+            }
+            ParseError::EmptyLambdaBody(_) => write!(fmt, "the body of a lambda was empty"),
+            ParseError::UnclosedBracket(_) => write!(fmt, "this bracket is never closed"),
+            ParseError::EmptyExpression => write!(fmt, "expression was empty"),
+            ParseError::UnexpectedEquals(_) => {
+                write!(
+                    fmt,
+                    "an `=` was found that does not follow the name of a definition"
+                )
+            }
+            ParseError::EmptyDefinition(_) => write!(fmt, "this definition has no expression"),
+            ParseError::MisplacedExpression(_) => write!(
+                fmt,
+                "an expression without a name has to be the last statement"
+            ),
+            ParseError::UnexpectedDefinition(_) => {
+                write!(fmt, "a single expression was expected, found a definition")
+            }
+            ParseError::MissingIdentifierAfterLet(_) => {
+                write!(fmt, "an identifier was expected after `let`")
+            }
+            ParseError::MissingEquals(_) => {
+                write!(fmt, "an `=` was expected after `let` and an identifier")
+            }
+            ParseError::MissingIn(_) => write!(fmt, "this `let` has no `in`"),
+            ParseError::UnexpectedIn(_) => write!(fmt, "an `in` was found without a `let`"),
+            ParseError::EmptyLetValue(_) => write!(fmt, "this `let` has no value"),
+            ParseError::EmptyLetBody(_) => write!(fmt, "this `let` has nothing after the `in`"),
+            ParseError::LexError(err) => write!(fmt, "{}", err),
+        }
+    }
+}
+
+impl Diagnostic for ParseError {
+    fn position(&self) -> Option<Position> {
+        match self {
+            ParseError::UnexpectedClosingBracket(position)
+            | ParseError::DotWithoutLambda(position)
+            | ParseError::MissingIdentifierAfterLambda(position)
+            | ParseError::MissingDot(position)
+            | ParseError::EmptyParenthesis(position)
+            | ParseError::EmptyLambdaBody(position)
+            | ParseError::UnclosedBracket(position)
+            | ParseError::UnexpectedEquals(position)
+            | ParseError::EmptyDefinition(position)
+            | ParseError::MisplacedExpression(position)
+            | ParseError::UnexpectedDefinition(position)
+            | ParseError::MissingIdentifierAfterLet(position)
+            | ParseError::MissingEquals(position)
+            | ParseError::MissingIn(position)
+            | ParseError::UnexpectedIn(position)
+            | ParseError::EmptyLetValue(position)
+            | ParseError::EmptyLetBody(position) => Some(*position),
+            ParseError::EmptyExpression => None,
+            ParseError::LexError(err) => err.position(),
+        }
+    }
+}
+
+/// Renders diagnostics in the style of rustc:
+///
+/// ```text
+/// error: unexpected closing bracket
+///  --> exercise.lc:1:5
+///   |
+/// 1 | λx.x) y
+///   |     ^
+/// ```
+pub struct Renderer<'a> {
+    file_name: &'a str,
+    source: &'a str,
+    colour: bool,
+}
+
+impl<'a> Renderer<'a> {
+    /// `source` has to be the exact text that was lexed so that the positions line up.
+    pub fn new(file_name: &'a str, source: &'a str) -> Self {
+        Renderer {
+            file_name,
+            source,
+            colour: false,
+        }
+    }
+
+    /// Turns ANSI colours on or off. They are off by default.
+    pub fn colour(mut self, colour: bool) -> Self {
+        self.colour = colour;
+        self
+    }
+
+    pub fn render<D>(&self, diagnostic: &D) -> String
+    where
+        D: Diagnostic + ?Sized,
+    {
+        self.render_message(&diagnostic.to_string(), diagnostic.position())
+    }
+
+    /// Renders an arbitrary message with an optional position. Rows and columns in `position`
+    /// start from zero like the ones the lexer produces, but they are shown starting from one.
+    pub fn render_message(&self, message: &str, position: Option<Position>) -> String {
+        self.render_labelled("error", RED, message, position)
+    }
+
+    /// Like `render_message`, but for pointing something out that is not an error.
+    pub fn render_note(&self, message: &str, position: Option<Position>) -> String {
+        self.render_labelled("note", GREEN, message, position)
+    }
+
+    fn render_labelled(
+        &self,
+        label: &str,
+        colour: &'static str,
+        message: &str,
+        position: Option<Position>,
+    ) -> String {
+        let mut out = format!(
+            "{}{}{}: {}{}{}\n",
+            self.paint(colour),
+            label,
+            self.paint(RESET),
+            self.paint(BOLD),
+            message,
+            self.paint(RESET)
+        );
+        let position = match position {
+            Some(position) => position,
+            None => {
+                out.push_str(&format!(
+                    "{} --> {}{}\n",
+                    self.paint(BLUE),
+                    self.paint(RESET),
+                    self.file_name
+                ));
+                return out;
+            }
+        };
+        let start = position.starting_point;
+        let line_number = (start.row + 1).to_string();
+        let gutter = " ".repeat(line_number.len());
+        out.push_str(&format!(
+            "{}{}--> {}{}:{}:{}\n",
+            gutter,
+            self.paint(BLUE),
+            self.paint(RESET),
+            self.file_name,
+            start.row + 1,
+            start.column + 1
+        ));
+        out.push_str(&format!(
+            "{} {}|{}\n",
+            gutter,
+            self.paint(BLUE),
+            self.paint(RESET)
+        ));
+        let line = self.source.lines().nth(start.row).unwrap_or("");
+        out.push_str(&format!(
+            "{}{} |{} {}\n",
+            self.paint(BLUE),
+            line_number,
+            self.paint(RESET),
+            line
+        ));
+        out.push_str(&format!(
+            "{} {}|{} {}{}{}\n",
+            gutter,
+            self.paint(BLUE),
+            self.paint(RESET),
+            self.paint(colour),
+            Self::underline(line, position),
+            self.paint(RESET)
+        ));
+        out
+    }
+
+    /// Builds the `^^^` line. Tabs before the span are kept so that the carets line up with
+    /// the text above them. A span that continues on later lines is underlined to the end of
+    /// its first line.
+    fn underline(line: &str, position: Position) -> String {
+        let start = position.starting_point;
+        let end = position.ending_point;
+        let line_length = line.chars().count();
+        let last_column = if end.row > start.row {
+            line_length.saturating_sub(1).max(start.column)
+        } else {
+            end.column.max(start.column)
+        };
+        let mut underline: String = line
+            .chars()
+            .chain(std::iter::repeat(' '))
+            .take(start.column)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        underline.push_str(&"^".repeat(last_column - start.column + 1));
+        underline
+    }
+
+    fn paint(&self, code: &'static str) -> &'static str {
+        if self.colour {
+            code
+        } else {
+            ""
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Renderer;
+    use lexer::{LexError, Point, Position};
+    use parser::ParseError;
+
+    fn position(from: (usize, usize), to: (usize, usize)) -> Position {
+        Position::new(Point::new(from.0, from.1), Point::new(to.0, to.1))
+    }
+
+    #[test]
+    fn render_parse_error() {
+        let source = "λx.x\n(λy.y)) z\n";
+        let error = ParseError::UnexpectedClosingBracket(position((1, 6), (1, 6)));
+        let rendered = Renderer::new("exercise.lc", source).render(&error);
+        assert_eq!(
+            rendered,
+            "error: unexpected closing bracket\n \
+             --> exercise.lc:2:7\n  \
+             |\n\
+             2 | (λy.y)) z\n  \
+             |       ^\n"
+        );
+    }
+
+    #[test]
+    fn render_lex_error_spanning_several_columns() {
+        let source = "\tλx.xyz";
+        let error = ParseError::LexError(LexError::IdentifierError(
+            "bad identifier".into(),
+            position((0, 4), (0, 6)),
+        ));
+        let rendered = Renderer::new("-", source).render(&error);
+        assert_eq!(
+            rendered,
+            "error: bad identifier\n \
+             --> -:1:5\n  \
+             |\n\
+             1 | \tλx.xyz\n  \
+             | \t   ^^^\n"
+        );
+    }
+
+    #[test]
+    fn render_error_without_position() {
+        let rendered = Renderer::new("empty.lc", "").render(&ParseError::EmptyExpression);
+        assert_eq!(rendered, "error: expression was empty\n --> empty.lc\n");
+    }
+
+    #[test]
+    fn render_with_colour() {
+        let error = LexError::InvalidCharacterError('$', position((0, 0), (0, 0)));
+        let rendered = Renderer::new("a.lc", "$").colour(true).render(&error);
+        assert!(rendered.starts_with("\u{1b}[1;31merror\u{1b}[0m: "));
+        assert!(rendered.contains("\u{1b}[1;31m^\u{1b}[0m"));
+    }
+
+    #[test]
+    fn render_note() {
+        let rendered = Renderer::new("a.lc", "f (λx.x) y")
+            .colour(true)
+            .render_note("next redex", Some(position((0, 2), (0, 9))));
+        assert!(rendered.starts_with("\u{1b}[1;32mnote\u{1b}[0m: "));
+        assert!(rendered.contains("\u{1b}[1;32m  ^^^^^^^^\u{1b}[0m"));
+    }
+}
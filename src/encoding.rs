@@ -0,0 +1,70 @@
+use parser::Expression;
+
+/// The largest numeric literal the lexer accepts. Every encoding nests its numerals about
+/// as deep as their value, and a term that deep overflows the stack of the functions that
+/// walk it.
+pub const MAX_LITERAL: usize = 4096;
+
+/// How the parser turns a numeric literal like `3` into a lambda term.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum NumeralEncoding {
+    /// `n = λf x.f (f ... (f x))` with `n` applications of `f`.
+    #[default]
+    Church,
+    /// `0 = λs z.z` and `n + 1 = λs z.s n`.
+    Scott,
+    /// `0 = λs z.z` and `n + 1 = λs z.s n (n s z)`. The size of the term doubles with every
+    /// successor, so large Parigot numerals get very big.
+    Parigot,
+}
+
+impl NumeralEncoding {
+    /// The largest number this encoding turns into a numeral.
+    pub fn max_literal(self) -> usize {
+        match self {
+            NumeralEncoding::Church | NumeralEncoding::Scott => MAX_LITERAL,
+            NumeralEncoding::Parigot => 16,
+        }
+    }
+
+    pub fn encode(self, n: usize) -> Expression {
+        match self {
+            NumeralEncoding::Church => {
+                let mut body = variable("x");
+                for _ in 0..n {
+                    body = Expression::Application(vec![variable("f"), body]);
+                }
+                lambda("f", lambda("x", body))
+            }
+            NumeralEncoding::Scott => {
+                let mut numeral = lambda("s", lambda("z", variable("z")));
+                for _ in 0..n {
+                    let body = Expression::Application(vec![variable("s"), numeral]);
+                    numeral = lambda("s", lambda("z", body));
+                }
+                numeral
+            }
+            NumeralEncoding::Parigot => {
+                let mut numeral = lambda("s", lambda("z", variable("z")));
+                for _ in 0..n {
+                    let recursion = Expression::Application(vec![
+                        numeral.clone(),
+                        variable("s"),
+                        variable("z"),
+                    ]);
+                    let body = Expression::Application(vec![variable("s"), numeral, recursion]);
+                    numeral = lambda("s", lambda("z", body));
+                }
+                numeral
+            }
+        }
+    }
+}
+
+fn variable(name: &str) -> Expression {
+    Expression::Variable(name.into())
+}
+
+fn lambda(name: &str, body: Expression) -> Expression {
+    Expression::Lambda(name.into(), Box::new(body))
+}
@@ -1,23 +1,19 @@
-use std::io::{BufReader, BufRead};
+use std::io::{self, BufReader, BufRead};
 use std::fs::File;
 use common::Source;
 
-fn load_file_as_char_source() -> CharFile {
-    
-}
-
 pub struct CharFile {
     line: String,
     file: BufReader<File>,
 }
 
 impl CharFile {
-    pub fn new(path: &str) -> Self {
-        let mut file = BufReader::new(File::open(path).unwrap());
-        CharFile {
+    pub fn new(path: &str) -> io::Result<Self> {
+        let file = BufReader::new(File::open(path)?);
+        Ok(CharFile {
             line: String::new(),
             file,
-        }
+        })
     }
 }
 
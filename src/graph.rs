@@ -0,0 +1,222 @@
+use debruijn::to_de_bruijn;
+use interpreter::{redexes, reducts, StepKind};
+use parser::Expression;
+use pretty::Format;
+use std::collections::{HashMap, VecDeque};
+
+/// How far `ReductionGraph::explore` goes.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Limits {
+    /// The most steps away from the start a term can be to have its reducts explored.
+    pub depth: usize,
+    /// The most terms in the graph.
+    pub nodes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            depth: 10,
+            nodes: 100,
+        }
+    }
+}
+
+/// A term in a `ReductionGraph`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Node {
+    /// The term as it was first reached, terms that only differ in the names of their bound
+    /// variables share a node.
+    pub term: Expression,
+    /// The fewest steps it takes to get to the term from the start.
+    pub depth: usize,
+    pub normal_form: bool,
+    /// Whether every reduct of the term is in the graph, which the limits can prevent.
+    pub explored: bool,
+}
+
+/// A step from the term of one node to the term of another.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    /// The path to the contracted redex, see `SpanTree::get` for how paths work.
+    pub path: Vec<usize>,
+}
+
+/// Every term a term reduces to in any number of beta steps, with every step between them.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReductionGraph {
+    /// The start is the first node and the others follow in the order they were reached.
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl ReductionGraph {
+    /// Contracts every redex of every term reached so far, breadth first from `expr`, until
+    /// there is nothing new or `limits` stop it.
+    pub fn explore(expr: Expression, limits: Limits) -> Self {
+        let mut graph = ReductionGraph {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        };
+        let mut indices = HashMap::new();
+        let mut queue = VecDeque::new();
+        indices.insert(to_de_bruijn(&expr), 0);
+        graph.add_node(expr, 0);
+        queue.push_back(0);
+        while let Some(from) = queue.pop_front() {
+            let depth = graph.nodes[from].depth;
+            if depth >= limits.depth {
+                continue;
+            }
+            let mut explored = true;
+            for (path, reduct) in reducts(&graph.nodes[from].term) {
+                let key = to_de_bruijn(&reduct);
+                let to = match indices.get(&key) {
+                    Some(&to) => to,
+                    None if graph.nodes.len() >= limits.nodes => {
+                        explored = false;
+                        continue;
+                    }
+                    None => {
+                        let to = graph.add_node(reduct, depth + 1);
+                        indices.insert(key, to);
+                        queue.push_back(to);
+                        to
+                    }
+                };
+                graph.edges.push(Edge { from, to, path });
+            }
+            graph.nodes[from].explored = explored;
+        }
+        graph
+    }
+
+    fn add_node(&mut self, term: Expression, depth: usize) -> usize {
+        let normal_form = redexes(&term, StepKind::Beta).is_empty();
+        self.nodes.push(Node {
+            term,
+            depth,
+            normal_form,
+            explored: normal_form,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Whether the limits left out any terms.
+    pub fn is_complete(&self) -> bool {
+        self.nodes.iter().all(|node| node.explored)
+    }
+
+    /// The graph in the DOT language of Graphviz. Normal forms get a double border and
+    /// terms whose reducts were not all explored a dashed one. Edges are labelled with the
+    /// path to the contracted redex, its child indices joined with dots, or `ε` for a redex
+    /// that is the whole term.
+    pub fn to_dot(&self, format: Format) -> String {
+        let mut dot = String::from("digraph reductions {\n    node [shape=box];\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let mut attributes = format!("label=\"{}\"", escape(&format.show(&node.term)));
+            if node.normal_form {
+                attributes.push_str(", peripheries=2");
+            } else if !node.explored {
+                attributes.push_str(", style=dashed");
+            }
+            dot.push_str(&format!("    n{} [{}];\n", i, attributes));
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "    n{} -> n{} [label=\"{}\"];\n",
+                edge.from,
+                edge.to,
+                path_label(&edge.path)
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn path_label(path: &[usize]) -> String {
+    if path.is_empty() {
+        return "ε".to_string();
+    }
+    let indices: Vec<String> = path.iter().map(|i| i.to_string()).collect();
+    indices.join(".")
+}
+
+/// Escapes `label` for a quoted DOT string.
+pub fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Limits, ReductionGraph};
+    use parser::test::lex_and_parse;
+    use pretty::{Format, LambdaStyle};
+
+    #[test]
+    fn write_dot() {
+        let graph =
+            ReductionGraph::explore(lex_and_parse("(λx.x) ((λy.y y) z)"), Limits::default());
+        let expected = "\
+digraph reductions {
+    node [shape=box];
+    n0 [label=\"(λx.x) ((λy.y y) z)\"];
+    n1 [label=\"(λy.y y) z\"];
+    n2 [label=\"(λx.x) (z z)\"];
+    n3 [label=\"z z\", peripheries=2];
+    n0 -> n1 [label=\"ε\"];
+    n0 -> n2 [label=\"1\"];
+    n1 -> n3 [label=\"ε\"];
+    n2 -> n3 [label=\"ε\"];
+}
+";
+        assert_eq!(graph.to_dot(Format::default()), expected);
+        let dot = graph.to_dot(Format::Pretty {
+            lambda_style: LambdaStyle::Backslash,
+            lets: false,
+        });
+        assert!(dot.contains("n1 [label=\"(\\\\y.y y) z\"];"));
+    }
+
+    #[test]
+    fn identify_alpha_equivalent_terms() {
+        let graph =
+            ReductionGraph::explore(lex_and_parse("(λx.x x) ((λy.y) z)"), Limits::default());
+        assert_eq!(graph.nodes.len(), 6);
+        assert_eq!(graph.edges.len(), 7);
+        assert!(graph.is_complete());
+        let normal_forms: Vec<_> = graph.nodes.iter().filter(|n| n.normal_form).collect();
+        assert_eq!(normal_forms.len(), 1);
+        assert_eq!(normal_forms[0].term.to_string(), "z z");
+        assert_eq!(normal_forms[0].depth, 2);
+        let graph = ReductionGraph::explore(lex_and_parse("(λx.x) ((λy.y) z)"), Limits::default());
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(graph.edges[0].to, graph.edges[1].to);
+        let graph = ReductionGraph::explore(lex_and_parse("(λx.x x) (λy.y y)"), Limits::default());
+        assert_eq!(graph.nodes.len(), 1);
+        assert_eq!(graph.edges.len(), 1);
+    }
+
+    #[test]
+    fn stop_at_the_limits() {
+        let growing = lex_and_parse("(λx.x x x) (λx.x x x)");
+        let limits = Limits {
+            depth: 3,
+            nodes: 100,
+        };
+        let graph = ReductionGraph::explore(growing.clone(), limits);
+        assert!(!graph.is_complete());
+        assert_eq!(graph.nodes.iter().map(|n| n.depth).max(), Some(3));
+        assert!(graph.to_dot(Format::default()).contains("style=dashed"));
+        let limits = Limits {
+            depth: 100,
+            nodes: 5,
+        };
+        let graph = ReductionGraph::explore(growing, limits);
+        assert!(!graph.is_complete());
+        assert_eq!(graph.nodes.len(), 5);
+    }
+}
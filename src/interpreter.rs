@@ -1,22 +1,59 @@
-use parser::Expression;
+use debruijn::{from_de_bruijn, to_de_bruijn, DbExpr};
+use lexer::Position;
+use parser::{Expression, Program};
+use pretty::Format;
+use readback::{read_back, Value};
+use span::{span_inside, SpanTree};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
-struct NameGen {
-    cur: usize,
-}
-impl NameGen {
-    fn new() -> Self {
-        NameGen {
-            cur: 0,
+/// The names of the variables that occur free in `expr`.
+pub fn free_vars(expr: &Expression) -> HashSet<String> {
+    match expr {
+        Expression::Variable(i) => {
+            let mut vars = HashSet::new();
+            vars.insert(i.clone());
+            vars
+        }
+        Expression::Lambda(i, e) => {
+            let mut vars = free_vars(e);
+            vars.remove(i);
+            vars
         }
+        Expression::Application(v) => v.iter().flat_map(free_vars).collect(),
     }
+}
 
-    fn next(&mut self) -> String {
-        let string = format!("#{}", self.cur);
-        self.cur += 1;
-        string
+/// The names of the variables that the lambdas in `expr` bind.
+fn bound_vars(expr: &Expression) -> HashSet<String> {
+    match expr {
+        Expression::Variable(_) => HashSet::new(),
+        Expression::Lambda(i, e) => {
+            let mut vars = bound_vars(e);
+            vars.insert(i.clone());
+            vars
+        }
+        Expression::Application(v) => v.iter().flat_map(bound_vars).collect(),
     }
 }
 
+/// A name like `name` that is not in `taken`, made by putting a number after it: `x` gives
+/// `x1`, `x2` and so on, and so does `x1`.
+fn fresh_name(name: &str, taken: &HashSet<String>) -> String {
+    let base = match name.trim_end_matches(|c: char| c.is_ascii_digit()) {
+        "" => "x",
+        base => base,
+    };
+    (1..)
+        .map(|n| format!("{}{}", base, n))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap()
+}
+
 fn rename(expr: Expression, from: String, to: String) -> Expression {
     match expr {
         Expression::Variable(i) => {
@@ -41,115 +78,1292 @@ fn rename(expr: Expression, from: String, to: String) -> Expression {
     }
 }
 
-fn alpha_conversion(expr: Expression, to: String) -> Expression {
-    match expr {
-        Expression::Lambda(i, e) => {
-            if i == to {
-                Expression::Lambda(i, e)
-            } else {
-                if identifier_exists(&*e, to.clone()) {
-                    println!("tried to alpha convert into a free or binding variable");
-                    panic!();
-                }
-                Expression::Lambda(to.clone(), Box::new(rename(*e, i, to)))
-            }
-        }
-        _ => {
-            println!("Tried to alpha convert something other than a lambda abstraction");
-            panic!();
-        }
-    }
+/// Replaces the free occurrences of `from` in `expr` with `to`. A lambda is only renamed
+/// when `to` would otherwise be captured by it, that is when it binds a free variable of `to`
+/// and `from` occurs free in its body. The new name is not bound anywhere in the body either,
+/// because `rename` does not rename around the lambdas it meets.
+fn substitute(expr: Expression, from: &str, to: &Expression) -> Expression {
+    substitute_avoiding(expr, from, to, &free_vars(to))
 }
 
-fn identifier_exists(expr: &Expression, ident: String) -> bool {
-    let mut stack = Vec::new();
-    stack.push(expr);
-    while let Some(ref e) = stack.pop() {
-        match *e {
-            Expression::Application(v) => {
-                for se in v {
-                    stack.push(&se);
-                }
-            }
-            Expression::Lambda(i, se) => {
-                if i == &ident {
-                    return true;
-                }
-                stack.push(&*se);
-            }
-            Expression::Variable(i) => if i == &ident {
-                return true;
-            },
-        }
-    }
-    false
-}
-
-fn substitute(
+fn substitute_avoiding(
     expr: Expression,
-    from: String,
-    to: Expression,
-    name_gen: &mut NameGen,
+    from: &str,
+    to: &Expression,
+    to_free: &HashSet<String>,
 ) -> Expression {
     match expr {
         Expression::Variable(i) => {
             if i == from {
-                to
+                to.clone()
             } else {
                 Expression::Variable(i)
             }
         }
         Expression::Lambda(i, e) => {
             if i == from {
+                return Expression::Lambda(i, e);
+            }
+            let body_free = free_vars(&e);
+            if !body_free.contains(from) {
                 Expression::Lambda(i, e)
+            } else if to_free.contains(&i) {
+                let mut taken = bound_vars(&e);
+                taken.extend(body_free);
+                taken.extend(to_free.iter().cloned());
+                let name = fresh_name(&i, &taken);
+                let e = rename(*e, i, name.clone());
+                Expression::Lambda(name, Box::new(substitute_avoiding(e, from, to, to_free)))
             } else {
-                let alpha_converted = alpha_conversion(Expression::Lambda(i, e), name_gen.next());
-                match alpha_converted {
-                    Expression::Lambda(i, e) => {
-                        Expression::Lambda(i, Box::new(substitute(*e, from, to, name_gen)))
-                    }
-                    _ => unreachable!(),
-                }
+                Expression::Lambda(i, Box::new(substitute_avoiding(*e, from, to, to_free)))
             }
         }
         Expression::Application(v) => Expression::Application(
             v.into_iter()
-                .map(|e| substitute(e, from.clone(), to.clone(), name_gen))
+                .map(|e| substitute_avoiding(e, from, to, to_free))
                 .collect(),
         ),
     }
 }
 
-fn beta_reduce(expr: Expression, name_gen: &mut NameGen) -> Option<Expression> {
-    match expr {
-            Expression::Application(mut v) => {
-                if let Expression::Lambda(i, e) = v[0].clone() {
-                    v[0] = substitute(*e, i, v[1].clone(), name_gen);
-                    v.remove(1);
-                    Some(Expression::Application(v))
-                } else {
-                    for i in 0..v.len() {
-                        if let Some(e) = beta_reduce(v[i].clone(), name_gen) {
-                            v[i] = e;
-                            return Some(Expression::Application(v));
-                        }
+/// The order in which redexes are contracted. A redex is an application whose function is a
+/// lambda abstraction, like `(λx.M) N`.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Strategy {
+    /// The leftmost outermost redex first, also under lambdas. Arguments are substituted
+    /// unreduced. This finds the normal form whenever there is one, so `(λx.y) Ω` gives `y`.
+    #[default]
+    NormalOrder,
+    /// The leftmost innermost redex first, also under lambdas. The function and its argument
+    /// are normalised before they are applied, so `(λx.y) Ω` never terminates.
+    ApplicativeOrder,
+    /// Like normal order, but never under a lambda or in an argument. Stops at a weak head
+    /// normal form, so `λx.(λy.y) x` is left alone.
+    CallByName,
+    /// Like applicative order, but never under a lambda. The argument is reduced to a value
+    /// before it is substituted, and the result is a weak normal form.
+    CallByValue,
+    /// Only contracts the head redex, under lambdas too. Stops at a head normal form
+    /// `λx1 ... xn.y M1 ... Mm` without reducing the arguments `Mi`.
+    HeadReduction,
+    /// Contracts every redex in the term at once, inner ones first, in a single step. Redexes
+    /// that are created by a step are left to the next one. Like normal order, this finds the
+    /// normal form whenever there is one.
+    Full,
+}
+
+/// How terms are represented while they are reduced.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Backend {
+    /// With names, keeping track of where every part of the term came from in the source.
+    #[default]
+    Named,
+    /// With de Bruijn indices, see `DbExpr`. Nothing has to be renamed, but the positions in
+    /// the source and the names of bound variables are lost.
+    DeBruijn,
+}
+
+/// What a strategy needs to know about a term to find its redexes.
+pub enum Shape<'a, T: 'a> {
+    Variable,
+    Lambda(&'a T),
+    Application(&'a [T]),
+}
+
+/// A representation of lambda terms that the strategies can find redexes in.
+pub trait Term: Sized {
+    fn shape(&self) -> Shape<'_, Self>;
+
+    /// Whether this is `λx.M x` where `x` does not occur free in `M`.
+    fn is_eta_redex(&self) -> bool;
+}
+
+impl Term for Expression {
+    fn shape(&self) -> Shape<'_, Self> {
+        match self {
+            Expression::Variable(_) => Shape::Variable,
+            Expression::Lambda(_, e) => Shape::Lambda(e),
+            Expression::Application(v) => Shape::Application(v),
+        }
+    }
+
+    fn is_eta_redex(&self) -> bool {
+        match self {
+            Expression::Lambda(x, e) => match &**e {
+                Expression::Application(v) => match v.split_last() {
+                    Some((Expression::Variable(i), function)) if i == x => {
+                        !function.is_empty() && !function.iter().any(|e| free_vars(e).contains(x))
                     }
-                    None
-                }
+                    _ => false,
+                },
+                _ => false,
             },
+            _ => false,
+        }
+    }
+}
+
+/// The kinds of reduction steps.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StepKind {
+    /// `(λx.M) N` to `M` with `N` substituted for `x`.
+    Beta,
+    /// `λx.M x` to `M`, if `x` does not occur free in `M`.
+    Eta,
+}
+
+impl fmt::Display for StepKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StepKind::Beta => write!(fmt, "β"),
+            StepKind::Eta => write!(fmt, "η"),
+        }
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    /// Reads a strategy by its name, like `normal-order`, or by a short name like `normal`
+    /// or `cbv`.
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "normal" | "normal-order" => Ok(Strategy::NormalOrder),
+            "applicative" | "applicative-order" => Ok(Strategy::ApplicativeOrder),
+            "cbn" | "call-by-name" => Ok(Strategy::CallByName),
+            "cbv" | "call-by-value" => Ok(Strategy::CallByValue),
+            "head" | "head-reduction" => Ok(Strategy::HeadReduction),
+            "full" => Ok(Strategy::Full),
+            _ => Err(format!(
+                "unknown strategy `{}`, expected one of normal, applicative, cbn, cbv, head \
+                 and full",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    /// Writes the short name `from_str` reads back.
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Strategy::NormalOrder => "normal",
+            Strategy::ApplicativeOrder => "applicative",
+            Strategy::CallByName => "cbn",
+            Strategy::CallByValue => "cbv",
+            Strategy::HeadReduction => "head",
+            Strategy::Full => "full",
+        };
+        write!(fmt, "{}", name)
+    }
+}
+
+impl Strategy {
+    fn reduces_under_lambdas(self) -> bool {
+        !matches!(self, Strategy::CallByName | Strategy::CallByValue)
+    }
+}
+
+fn is_redex<T: Term>(expr: &T) -> bool {
+    match expr.shape() {
+        Shape::Application(v) => v.len() >= 2 && matches!(v[0].shape(), Shape::Lambda(_)),
+        _ => false,
+    }
+}
+
+/// The path to the next redex `strategy` contracts, see `SpanTree::get` for how paths work.
+/// `Strategy::Full` contracts more than one redex at a time, this gives the outermost one.
+fn find_redex<T: Term>(expr: &T, strategy: Strategy) -> Option<Vec<usize>> {
+    let under_lambdas = strategy.reduces_under_lambdas();
+    let in_child = |i: usize, e: &T| {
+        find_redex(e, strategy).map(|mut path| {
+            path.insert(0, i);
+            path
+        })
+    };
+    match expr.shape() {
+        Shape::Variable => None,
+        Shape::Lambda(e) if under_lambdas => in_child(0, e),
+        Shape::Lambda(_) => None,
+        Shape::Application(v) => match strategy {
+            Strategy::NormalOrder | Strategy::Full => {
+                if is_redex(expr) {
+                    return Some(Vec::new());
+                }
+                v.iter().enumerate().find_map(|(i, e)| in_child(i, e))
+            }
+            Strategy::CallByName | Strategy::HeadReduction => {
+                if is_redex(expr) {
+                    return Some(Vec::new());
+                }
+                in_child(0, &v[0])
+            }
+            Strategy::ApplicativeOrder | Strategy::CallByValue => {
+                // `M N1 N2` is `(M N1) N2`, so the first argument is applied before the second
+                // one is reduced.
+                let function = v
+                    .iter()
+                    .take(2)
+                    .enumerate()
+                    .find_map(|(i, e)| in_child(i, e));
+                if function.is_some() {
+                    return function;
+                }
+                if is_redex(expr) {
+                    return Some(Vec::new());
+                }
+                v.iter()
+                    .enumerate()
+                    .skip(2)
+                    .find_map(|(i, e)| in_child(i, e))
+            }
+        },
+    }
+}
+
+/// The path to the leftmost outermost eta redex. Only looks under lambdas if `under_lambdas`
+/// is set, but `expr` itself can always be one.
+fn find_eta_redex<T: Term>(expr: &T, under_lambdas: bool) -> Option<Vec<usize>> {
+    if expr.is_eta_redex() {
+        return Some(Vec::new());
+    }
+    let children: Vec<&T> = match expr.shape() {
+        Shape::Variable => Vec::new(),
+        Shape::Lambda(e) if under_lambdas => vec![e],
+        Shape::Lambda(_) => Vec::new(),
+        Shape::Application(v) => v.iter().collect(),
+    };
+    children.into_iter().enumerate().find_map(|(i, e)| {
+        find_eta_redex(e, under_lambdas).map(|mut path| {
+            path.insert(0, i);
+            path
+        })
+    })
+}
+
+/// The paths to every part of `expr` that `is_redex` holds for, outer ones before the ones
+/// inside of them.
+fn all_redexes<T: Term>(expr: &T, is_redex: fn(&T) -> bool) -> Vec<Vec<usize>> {
+    let mut paths = Vec::new();
+    if is_redex(expr) {
+        paths.push(Vec::new());
+    }
+    let children: Vec<&T> = match expr.shape() {
+        Shape::Variable => Vec::new(),
+        Shape::Lambda(e) => vec![e],
+        Shape::Application(v) => v.iter().collect(),
+    };
+    for (i, child) in children.into_iter().enumerate() {
+        for mut path in all_redexes(child, is_redex) {
+            path.insert(0, i);
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// The paths to every redex of the given kind in `expr`, outer ones before the ones inside of
+/// them and otherwise from left to right.
+pub fn redexes<T: Term>(expr: &T, kind: StepKind) -> Vec<Vec<usize>> {
+    match kind {
+        StepKind::Beta => all_redexes(expr, is_redex),
+        StepKind::Eta => all_redexes(expr, T::is_eta_redex),
+    }
+}
+
+/// The part of `expr` at `path`, see `SpanTree::get` for how paths work.
+pub fn subterm<'a>(expr: &'a Expression, path: &[usize]) -> Option<&'a Expression> {
+    match (expr, path.split_first()) {
+        (_, None) => Some(expr),
+        (Expression::Lambda(_, e), Some((0, rest))) => subterm(e, rest),
+        (Expression::Application(v), Some((&i, rest))) => v.get(i).and_then(|e| subterm(e, rest)),
+        _ => None,
+    }
+}
+
+/// Contracts the beta or eta redex at `path` in `expr`, `None` if there is none there.
+/// Together with `redexes` this can take any reduction path, not only the one a strategy
+/// takes. A beta redex at the start of a longer application like `(λx.M) N P` is at the path
+/// of the whole application.
+pub fn contract_at(expr: Expression, path: &[usize]) -> Option<Expression> {
+    match subterm(&expr, path) {
+        Some(e) if is_redex(e) || e.is_eta_redex() => {}
+        _ => return None,
+    }
+    let spans = SpanTree::unknown(&expr);
+    Some(contract_with_spans_at(expr, spans, path).0)
+}
+
+/// Every term `expr` reduces to in a single beta step, together with the path to the redex
+/// that was contracted to get it.
+pub fn reducts(expr: &Expression) -> Vec<(Vec<usize>, Expression)> {
+    redexes(expr, StepKind::Beta)
+        .into_iter()
+        .map(|path| {
+            let reduct = contract_at(expr.clone(), &path).unwrap();
+            (path, reduct)
+        })
+        .collect()
+}
+
+/// Builds an application out of `v`, leaving out the application if there is only one
+/// expression and merging a leading application into it. `split` takes the expressions out
+/// of an application and gives back anything else as it is, `join` builds the application.
+/// Shared by the named and the de Bruijn terms.
+pub fn merge_application<T, S, J>(mut v: Vec<T>, split: S, join: J) -> T
+where
+    S: FnOnce(T) -> Result<Vec<T>, T>,
+    J: FnOnce(Vec<T>) -> T,
+{
+    if v.len() == 1 {
+        return v.remove(0);
+    }
+    match split(v.remove(0)) {
+        Ok(head) => {
+            v.splice(0..0, head);
+        }
+        Err(head) => v.insert(0, head),
+    }
+    join(v)
+}
+
+/// `merge_application` for expressions and their positions. `spans` are the positions of `v`.
+fn application(
+    v: Vec<Expression>,
+    spans: Vec<SpanTree>,
+    position: Option<Position>,
+) -> (Expression, SpanTree) {
+    merge_application(
+        v.into_iter().zip(spans).collect(),
+        |(e, s)| match e {
+            Expression::Application(v) => Ok(v.into_iter().zip(s.children).collect()),
+            e => Err((e, s)),
+        },
+        |v| {
+            let (v, spans) = v.into_iter().unzip();
+            (Expression::Application(v), SpanTree::new(position, spans))
+        },
+    )
+}
+
+/// Merges every application in function position into the application around it, so that a
+/// substituted `f a` in `x b` gives `f a b` and not `(f a) b`.
+fn flatten(expr: Expression, spans: SpanTree) -> (Expression, SpanTree) {
+    match expr {
+        Expression::Variable(_) => (expr, spans),
         Expression::Lambda(i, e) => {
-            beta_reduce(*e, name_gen)
+            let (e, s) = flatten(*e, spans.children.into_iter().next().unwrap());
+            (
+                Expression::Lambda(i, Box::new(e)),
+                SpanTree::new(spans.position, vec![s]),
+            )
+        }
+        Expression::Application(v) => {
+            let (v, children) = v
+                .into_iter()
+                .zip(spans.children)
+                .map(|(e, s)| flatten(e, s))
+                .unzip();
+            application(v, children, spans.position)
+        }
+    }
+}
+
+/// Contracts the beta or eta redex at `path`. Returns the position of the redex too when it
+/// is known.
+fn contract_with_spans_at(
+    expr: Expression,
+    spans: SpanTree,
+    path: &[usize],
+) -> (Expression, SpanTree, Option<Position>) {
+    let mut children = spans.children;
+    match (expr, path.split_first()) {
+        (Expression::Application(mut v), None) => {
+            let redex_position =
+                span_inside(spans.position, children[0].position, children[1].position);
+            let (i, e) = match v.remove(0) {
+                Expression::Lambda(i, e) => (i, e),
+                _ => unreachable!(),
+            };
+            let body_spans = children.remove(0).children.remove(0);
+            let body_spans = body_spans.substitute(&e, &i, &children[0]);
+            let body = substitute(*e, &i, &v[0]);
+            let (body, body_spans) = flatten(body, body_spans);
+            v[0] = body;
+            children[0] = body_spans;
+            let (e, s) = application(v, children, spans.position);
+            (e, s, redex_position)
+        }
+        (Expression::Lambda(_, e), None) => {
+            let mut v = match *e {
+                Expression::Application(v) => v,
+                _ => panic!("there is no redex at {:?}", path),
+            };
+            let application_spans = children.remove(0);
+            let mut function_spans = application_spans.children;
+            v.pop();
+            function_spans.pop();
+            let function_position = span_inside(
+                application_spans.position,
+                function_spans[0].position,
+                function_spans[function_spans.len() - 1].position,
+            );
+            let (e, s) = application(v, function_spans, function_position);
+            (e, s, spans.position)
+        }
+        (Expression::Lambda(i, e), Some((0, rest))) => {
+            let (e, s, p) = contract_with_spans_at(*e, children.remove(0), rest);
+            (
+                Expression::Lambda(i, Box::new(e)),
+                SpanTree::new(spans.position, vec![s]),
+                p,
+            )
+        }
+        (Expression::Application(mut v), Some((&i, rest))) => {
+            let (e, s, p) = contract_with_spans_at(v[i].clone(), children[i].clone(), rest);
+            v[i] = e;
+            children[i] = s;
+            (
+                Expression::Application(v),
+                SpanTree::new(spans.position, children),
+                p,
+            )
+        }
+        _ => panic!("there is no redex at {:?}", path),
+    }
+}
+
+/// The kind of the next step and the paths to the redexes it contracts, `None` if there are
+/// none. Eta steps are only taken if `options.eta` is set and there are no beta redexes left
+/// for the strategy.
+pub fn next_redexes<T: Term>(expr: &T, options: &Options) -> Option<(StepKind, Vec<Vec<usize>>)> {
+    let beta = match options.strategy {
+        Strategy::Full => all_redexes(expr, is_redex),
+        strategy => find_redex(expr, strategy).into_iter().collect(),
+    };
+    if !beta.is_empty() {
+        return Some((StepKind::Beta, beta));
+    }
+    if !options.eta {
+        return None;
+    }
+    let eta = match options.strategy {
+        Strategy::Full => all_redexes(expr, T::is_eta_redex),
+        strategy => find_eta_redex(expr, strategy.reduces_under_lambdas())
+            .into_iter()
+            .collect(),
+    };
+    if eta.is_empty() {
+        None
+    } else {
+        Some((StepKind::Eta, eta))
+    }
+}
+
+/// A term in one of the representations of `Backend`, together with whatever has to be
+/// reduced alongside it.
+trait Reducible: Sized {
+    type Term: Term;
+
+    fn term(&self) -> &Self::Term;
+
+    /// Contracts the beta or eta redex at `path`. Returns the position of the redex too when
+    /// it is known.
+    fn contract_at(self, path: &[usize]) -> (Self, Option<Position>);
+
+    fn expression(&self) -> Cow<'_, Expression>;
+
+    /// The term with numbered bound variables, to recognise it when it comes back.
+    fn key(&self) -> DbExpr;
+}
+
+/// A named term with the source positions of its parts.
+struct Named(Expression, SpanTree);
+
+impl Reducible for Named {
+    type Term = Expression;
+
+    fn term(&self) -> &Expression {
+        &self.0
+    }
+
+    fn contract_at(self, path: &[usize]) -> (Self, Option<Position>) {
+        let (e, s, p) = contract_with_spans_at(self.0, self.1, path);
+        (Named(e, s), p)
+    }
+
+    fn expression(&self) -> Cow<'_, Expression> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn key(&self) -> DbExpr {
+        to_de_bruijn(&self.0)
+    }
+}
+
+impl Reducible for DbExpr {
+    type Term = DbExpr;
+
+    fn term(&self) -> &DbExpr {
+        self
+    }
+
+    fn contract_at(self, path: &[usize]) -> (Self, Option<Position>) {
+        (DbExpr::contract_at(self, path), None)
+    }
+
+    fn expression(&self) -> Cow<'_, Expression> {
+        Cow::Owned(from_de_bruijn(self))
+    }
+
+    fn key(&self) -> DbExpr {
+        self.clone()
+    }
+}
+
+/// Contracts the redexes at `paths` in `expr`, whose source positions are `spans`. The paths
+/// have to be the ones of redexes in `expr` itself, like the ones `redexes` and
+/// `next_redexes` give. The known positions of the contracted redexes are returned too.
+pub fn contract_with_spans(
+    expr: Expression,
+    spans: SpanTree,
+    paths: Vec<Vec<usize>>,
+) -> (Expression, SpanTree, Vec<Position>) {
+    let (Named(e, s), positions) = contract_all(Named(expr, spans), paths);
+    (e, s, positions)
+}
+
+/// Contracts the redexes at `paths`, none of which may be created by contracting another.
+/// The known positions of the contracted redexes are returned too.
+fn contract_all<R: Reducible>(term: R, mut paths: Vec<Vec<usize>>) -> (R, Vec<Position>) {
+    // Contracting a redex only changes what is inside of it, so the paths to the redexes
+    // that are not inside of it stay the same if inner ones are contracted first.
+    paths.sort_by_key(|path| Reverse(path.len()));
+    let mut term = term;
+    let mut positions = Vec::new();
+    for path in paths {
+        let (t, p) = term.contract_at(&path);
+        term = t;
+        positions.extend(p);
+    }
+    positions.reverse();
+    (term, positions)
+}
+
+/// How much `interpret` prints on the way to the result.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Trace {
+    /// Only the result.
+    None,
+    /// The term after every step, labelled with the kind of step.
+    Steps,
+    /// Every step and where in the source the contracted redexes are.
+    #[default]
+    Redexes,
+}
+
+impl FromStr for Trace {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "none" => Ok(Trace::None),
+            "steps" => Ok(Trace::Steps),
+            "redexes" => Ok(Trace::Redexes),
+            _ => Err(format!(
+                "unknown trace `{}`, expected one of none, steps and redexes",
+                name
+            )),
+        }
+    }
+}
+
+/// How to reduce a term.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Options {
+    pub strategy: Strategy,
+    pub backend: Backend,
+    /// The most steps to take, `None` for no limit.
+    pub max_steps: Option<usize>,
+    /// How long to reduce for at most, `None` for no limit.
+    pub timeout: Option<Duration>,
+    /// What `interpret` prints besides the result.
+    pub trace: Trace,
+    /// How `interpret` prints terms.
+    pub format: Format,
+    /// Whether to stop when a term comes back up to renaming of bound variables.
+    pub detect_cycles: bool,
+    /// Whether to contract eta redexes too once there are no beta redexes left, so that the
+    /// result is a beta-eta normal form. They are contracted leftmost outermost first, or all
+    /// at once with `Strategy::Full`.
+    pub eta: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            strategy: Strategy::default(),
+            backend: Backend::default(),
+            max_steps: Some(10_000),
+            timeout: None,
+            trace: Trace::default(),
+            format: Format::default(),
+            detect_cycles: false,
+            eta: false,
+        }
+    }
+}
+
+/// How a reduction ended. Each outcome holds the term it ended with and the number of steps
+/// it took to get there.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Outcome {
+    /// The strategy has nothing left to contract.
+    NormalForm(Expression, usize),
+    /// The step limit was reached first.
+    OutOfFuel(Expression, usize),
+    /// The timeout was reached first.
+    Timeout(Expression, usize),
+    /// The term came back after the given number of steps, the last number is how many steps
+    /// ago it was seen before.
+    Cycle(Expression, usize, usize),
+}
+
+impl Outcome {
+    /// The term a reduction ended with, or why it stopped before reaching a normal form.
+    pub fn describe(&self, format: Format) -> String {
+        match self {
+            Outcome::NormalForm(e, _) => format.show(e),
+            Outcome::OutOfFuel(_, steps) => {
+                format!("no normal form after {} steps, giving up", steps)
+            }
+            Outcome::Timeout(_, steps) => {
+                format!("no normal form after {} steps, timed out", steps)
+            }
+            Outcome::Cycle(e, steps, length) => format!(
+                "reduction cycle of length {} detected at step {}, {} came back",
+                length,
+                steps,
+                format.show(e)
+            ),
+        }
+    }
+}
+
+/// Reduces `expr` as `options` say, calling `on_step` with the new term, the kind of step and
+/// the positions of the contracted redexes after every step. `spans` are the source positions of `expr`, use
+/// `SpanTree::unknown` if it did not come from a source. The new term is passed as a function
+/// that builds it, because the de Bruijn backend has to convert its terms back first.
+pub fn reduce<F>(expr: Expression, spans: SpanTree, options: &Options, on_step: F) -> Outcome
+where
+    F: for<'a> FnMut(&'a dyn Fn() -> Cow<'a, Expression>, StepKind, &[Position]),
+{
+    match options.backend {
+        Backend::Named => reduce_with(Named(expr, spans), options, on_step),
+        Backend::DeBruijn => reduce_with(to_de_bruijn(&expr), options, on_step),
+    }
+}
+
+fn reduce_with<R, F>(term: R, options: &Options, mut on_step: F) -> Outcome
+where
+    R: Reducible,
+    F: for<'a> FnMut(&'a dyn Fn() -> Cow<'a, Expression>, StepKind, &[Position]),
+{
+    let start = Instant::now();
+    let mut term = term;
+    let mut steps = 0;
+    let mut seen = HashMap::new();
+    loop {
+        let (kind, paths) = match next_redexes(term.term(), options) {
+            Some(next) => next,
+            None => return Outcome::NormalForm(term.expression().into_owned(), steps),
+        };
+        if options.detect_cycles {
+            if let Some(earlier) = seen.insert(term.key(), steps) {
+                return Outcome::Cycle(term.expression().into_owned(), steps, steps - earlier);
+            }
+        }
+        if options.max_steps.is_some_and(|max| steps >= max) {
+            return Outcome::OutOfFuel(term.expression().into_owned(), steps);
+        }
+        if options
+            .timeout
+            .is_some_and(|timeout| start.elapsed() >= timeout)
+        {
+            return Outcome::Timeout(term.expression().into_owned(), steps);
+        }
+        let (t, positions) = contract_all(term, paths);
+        steps += 1;
+        on_step(&|| t.expression(), kind, &positions);
+        term = t;
+    }
+}
+
+/// Reduces `expr` as `options` say, printing the steps as much as `options.trace` asks for.
+/// Prints the normal form read back as data or why the reduction stopped before reaching one.
+pub fn interpret(expr: Expression, spans: SpanTree, options: &Options) -> Outcome {
+    let format = options.format;
+    if options.trace != Trace::None {
+        println!("{}", format.show(&expr));
+    }
+    let outcome = reduce(expr, spans, options, |term, kind, redex_positions| {
+        match (options.trace, redex_positions.len()) {
+            (Trace::None, _) => {}
+            (Trace::Steps, _) | (Trace::Redexes, 0) => {
+                println!("{} {}", kind, format.show(&term()))
+            }
+            (Trace::Redexes, 1) => println!(
+                "{} {} (contracted the redex at {})",
+                kind,
+                format.show(&term()),
+                redex_positions[0]
+            ),
+            (Trace::Redexes, _) => {
+                let positions: Vec<String> =
+                    redex_positions.iter().map(|p| p.to_string()).collect();
+                println!(
+                    "{} {} (contracted the redexes at {})",
+                    kind,
+                    format.show(&term()),
+                    positions.join(", ")
+                );
+            }
+        }
+    });
+    match &outcome {
+        Outcome::NormalForm(e, _) => match read_back(e) {
+            Value::Term(e) => println!("= {}", format.show(&e)),
+            value => println!("= {}", value),
         },
-        Expression::Variable(i) => None,
+        _ => println!("{}", outcome.describe(format)),
     }
+    outcome
 }
 
-pub fn interpret(expr: Expression) {
-    println!("{:?}", expr);
-    let mut expression = expr;
-    let mut name_gen = NameGen::new();
-    while let Some(e) = beta_reduce(expression.clone(), &mut name_gen) {
-        println!("{:?}", e);
-        expression = e;
+/// Replaces every free occurrence of a defined name in the main expression of `program` with
+/// its definition. A definition can refer to the ones before it, and a later definition of
+/// the same name shadows an earlier one. Returns `None` if there is no main expression.
+pub fn expand_definitions(program: Program) -> Option<(Expression, SpanTree)> {
+    let (mut expression, mut spans) = program.main?;
+    for definition in program.definitions.into_iter().rev() {
+        spans = spans.substitute(&expression, &definition.name, &definition.spans);
+        expression = substitute(expression, &definition.name, &definition.expression);
+    }
+    Some((expression, spans))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        contract_at, contract_with_spans, expand_definitions, find_redex, free_vars, redexes,
+        reduce, reducts, Backend, Options, Outcome, StepKind, Strategy,
+    };
+    use alpha::alpha_eq;
+    use debruijn::to_de_bruijn;
+    use parser::test::{lex_and_parse, lex_and_parse_program};
+    use parser::Expression;
+    use readback::read_back;
+    use span::SpanTree;
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    const OMEGA: &str = "((λx.x x) (λx.x x))";
+
+    const PRELUDE: &str = "
+        TRUE = λt f.t; FALSE = λt f.f;
+        NOT = λb.b FALSE TRUE; AND = λa b.a b FALSE; OR = λa b.a TRUE b;
+        PAIR = λa b s.s a b; FST = λp.p TRUE; SND = λp.p FALSE;
+        SUCC = λn f x.f (n f x); PLUS = λm n f x.m f (n f x);
+        MULT = λm n f.m (n f); POW = λb e.e b;
+        PRED = λn f x.n (λg h.h (g f)) (λu.x) (λu.u); SUB = λm n.n PRED m;
+        ISZERO = λn.n (λx.FALSE) TRUE;
+        NIL = λc n.n; CONS = λh t c n.c h (t c n);
+        I = λx.x; K = λx y.x; S = λx y z.x z (y z);
+        Y = λf.(λx.f (x x)) (λx.f (x x));
+        FACT = Y (λr n.ISZERO n 1 (MULT n (r (PRED n))));
+    ";
+
+    /// Reduces with `strategy` for at most `steps` steps, returns `None` if it did not stop.
+    fn lex_parse_and_reduce(
+        string_slice: &str,
+        strategy: Strategy,
+        steps: usize,
+    ) -> Option<Expression> {
+        let expression = lex_and_parse(string_slice);
+        let spans = SpanTree::unknown(&expression);
+        let options = Options {
+            strategy,
+            max_steps: Some(steps),
+            ..Options::default()
+        };
+        match reduce(expression, spans, &options, |_, _, _| {}) {
+            Outcome::NormalForm(e, _) => Some(e),
+            _ => None,
+        }
+    }
+
+    fn lex_parse_and_expand(string_slice: &str) -> Expression {
+        expand_definitions(lex_and_parse_program(string_slice))
+            .unwrap()
+            .0
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn report_redex_positions() {
+        let positions = |string_slice: &str| {
+            let (expression, spans) =
+                expand_definitions(lex_and_parse_program(string_slice)).unwrap();
+            let (_, _, positions) = contract_with_spans(expression, spans, vec![Vec::new()]);
+            positions[0].to_string()
+        };
+        assert_eq!(positions("(λx.x) (λy.y)"), "1:2-1:12");
+        assert_eq!(positions("I = λx.x;\n-- c\nI (λy.y)"), "3:1-3:7");
+        assert_eq!(positions("A = λx.x; B = b;\nA B"), "2:1-2:3");
+    }
+
+    #[test]
+    fn expand_definitions_in_order() {
+        let expression = lex_parse_and_expand("A = a; B = A b; A = c; B A");
+        let expected = Expression::Application(vec![
+            Expression::Application(vec![
+                Expression::Variable("a".into()),
+                Expression::Variable("b".into()),
+            ]),
+            Expression::Variable("c".into()),
+        ]);
+        assert_eq!(expression, expected);
+    }
+
+    /// Expands `PRELUDE` in `string_slice` and normalises it as `options` say.
+    fn normal_form_with_prelude(string_slice: &str, options: &Options) -> Expression {
+        let program = lex_and_parse_program(&format!("{} {}", PRELUDE, string_slice));
+        let (expression, spans) = expand_definitions(program).unwrap();
+        match reduce(expression, spans, options, |_, _, _| {}) {
+            Outcome::NormalForm(e, _) => e,
+            outcome => panic!("{} has no normal form: {:?}", string_slice, outcome),
+        }
+    }
+
+    #[test]
+    fn find_free_variables() {
+        let vars = free_vars(&lex_and_parse("λx.x y (λy.y z) w"));
+        let mut vars: Vec<_> = vars.into_iter().collect();
+        vars.sort();
+        assert_eq!(vars, vec!["w", "y", "z"]);
+    }
+
+    #[test]
+    fn rename_only_capturing_binders() {
+        let cases = [
+            ("(λx y.x) z", "λy.z"),
+            ("(λx y.x) y", "λy1.y"),
+            ("(λx y1 y.x y1) y", "λy1 y2.y y1"),
+            ("(λx y y1.x y) y", "λy2 y1.y y2"),
+            ("(λx y.y) y", "λy.y"),
+            ("(λx.x (λx.x)) y", "y (λx.x)"),
+            ("(λx x1.λx.x1 x) x", "λx1 x.x1 x"),
+            ("(λf y.f y) (g y y1)", "λy2.g y y1 y2"),
+            ("S K K", "λz.z"),
+        ];
+        let de_bruijn = Options {
+            backend: Backend::DeBruijn,
+            ..Options::default()
+        };
+        for &(term, expected) in &cases {
+            let result = normal_form_with_prelude(term, &Options::default());
+            assert_eq!(result, lex_and_parse(expected), "{}", term);
+            assert!(
+                alpha_eq(&result, &normal_form_with_prelude(term, &de_bruijn)),
+                "{}",
+                term
+            );
+        }
+    }
+
+    #[test]
+    fn keep_binders_when_reducing_under_lambdas() {
+        assert_eq!(
+            lex_parse_and_reduce("λx.(λy.y) x", Strategy::NormalOrder, 100),
+            Some(lex_and_parse("λx.x"))
+        );
+        assert_eq!(
+            lex_parse_and_reduce("λx y.(λz.z) y", Strategy::NormalOrder, 100),
+            Some(lex_and_parse("λx y.y"))
+        );
+        assert_eq!(
+            lex_parse_and_reduce("(λx.x b) (f a)", Strategy::NormalOrder, 100),
+            Some(lex_and_parse("f a b"))
+        );
+    }
+
+    #[test]
+    fn known_normal_forms() {
+        let cases = [
+            ("SUCC 0", "1"),
+            ("PLUS 2 3", "5"),
+            ("MULT 2 3", "6"),
+            ("POW 2 3", "8"),
+            ("PRED 3", "2"),
+            ("SUB 5 2", "3"),
+            ("FACT 3", "6"),
+            ("NOT FALSE", "true"),
+            ("AND TRUE TRUE", "true"),
+            ("OR FALSE TRUE", "true"),
+            ("AND TRUE FALSE 1 2", "2"),
+            ("ISZERO 0", "true"),
+            ("ISZERO 2 1 2", "2"),
+            ("FST (PAIR 1 2)", "1"),
+            ("PAIR (SND (PAIR 1 2)) TRUE", "(2, true)"),
+            ("CONS 1 (CONS (PLUS 1 1) NIL)", "[1, 2]"),
+            ("S K K 3", "3"),
+            ("K 1 ((λx.x x) (λx.x x))", "1"),
+        ];
+        for &(term, expected) in &cases {
+            let value = read_back(&normal_form_with_prelude(term, &Options::default()));
+            assert_eq!(value.to_string(), expected, "{}", term);
+        }
+    }
+
+    #[test]
+    fn reduce_with_de_bruijn_indices() {
+        let terms = [
+            "PLUS 2 3",
+            "SUB 5 2",
+            "CONS 1 (CONS (PLUS 1 1) NIL)",
+            "(λf y.f y) (g y y1)",
+            "λx y.(λz.z) y",
+            "(λx.x b) (f a)",
+        ];
+        let strategies = [
+            Strategy::NormalOrder,
+            Strategy::ApplicativeOrder,
+            Strategy::HeadReduction,
+            Strategy::Full,
+        ];
+        for &term in &terms {
+            for &strategy in &strategies {
+                let named = Options {
+                    strategy,
+                    ..Options::default()
+                };
+                let de_bruijn = Options {
+                    backend: Backend::DeBruijn,
+                    ..named
+                };
+                let expected = normal_form_with_prelude(term, &named);
+                let result = normal_form_with_prelude(term, &de_bruijn);
+                assert!(alpha_eq(&result, &expected), "{} {:?}", term, strategy);
+            }
+        }
+    }
+
+    #[test]
+    fn eta_reduce() {
+        let cases = [
+            ("λx.f x", "f"),
+            ("λx y.f x y", "f"),
+            ("λx.x x", "λx.x x"),
+            ("λx.f x x", "λx.f x x"),
+            ("λx.(λy.g y) x", "g"),
+            ("λz.λx.z (λy.z y) x", "λz.z z"),
+            ("λx.(λy.y) x", "λx.x"),
+            ("(λf.λx.f x) (λy.g y)", "g"),
+        ];
+        for &backend in &[Backend::Named, Backend::DeBruijn] {
+            for &strategy in &[Strategy::NormalOrder, Strategy::Full] {
+                let options = Options {
+                    strategy,
+                    backend,
+                    eta: true,
+                    ..Options::default()
+                };
+                for &(term, expected) in &cases {
+                    let result = normal_form_with_prelude(term, &options);
+                    assert!(
+                        alpha_eq(&result, &lex_and_parse(expected)),
+                        "{} {:?} {:?}",
+                        term,
+                        strategy,
+                        backend
+                    );
+                }
+            }
+        }
+        let term = "λx.f x";
+        let result = normal_form_with_prelude(term, &Options::default());
+        assert_eq!(result, lex_and_parse(term));
+    }
+
+    #[test]
+    fn label_steps() {
+        let expression = lex_and_parse("λx.(λy.f y) x");
+        let options = Options {
+            eta: true,
+            ..Options::default()
+        };
+        let mut kinds = Vec::new();
+        reduce(
+            expression.clone(),
+            SpanTree::unknown(&expression),
+            &options,
+            |_, kind, _| kinds.push(kind.to_string()),
+        );
+        assert_eq!(kinds, vec!["β", "η"]);
+    }
+
+    #[test]
+    fn unused_divergent_argument() {
+        let term = format!("(λx.y) {}", OMEGA);
+        let y = Expression::Variable("y".into());
+        for &strategy in &[
+            Strategy::NormalOrder,
+            Strategy::CallByName,
+            Strategy::HeadReduction,
+            Strategy::Full,
+        ] {
+            assert_eq!(lex_parse_and_reduce(&term, strategy, 100), Some(y.clone()));
+        }
+        for &strategy in &[Strategy::ApplicativeOrder, Strategy::CallByValue] {
+            assert_eq!(lex_parse_and_reduce(&term, strategy, 100), None);
+        }
+    }
+
+    #[test]
+    fn reduce_under_lambdas() {
+        let term = "λx.(λy.y) x";
+        let expected = lex_and_parse("λx.x");
+        for &strategy in &[
+            Strategy::NormalOrder,
+            Strategy::ApplicativeOrder,
+            Strategy::HeadReduction,
+            Strategy::Full,
+        ] {
+            assert_eq!(
+                lex_parse_and_reduce(term, strategy, 100),
+                Some(expected.clone())
+            );
+        }
+        for &strategy in &[Strategy::CallByName, Strategy::CallByValue] {
+            assert_eq!(
+                lex_parse_and_reduce(term, strategy, 100),
+                Some(lex_and_parse(term))
+            );
+        }
+    }
+
+    #[test]
+    fn head_reduction_leaves_arguments() {
+        let term = "x ((λy.y) z)";
+        assert_eq!(
+            lex_parse_and_reduce(term, Strategy::HeadReduction, 100),
+            Some(lex_and_parse(term))
+        );
+        assert_eq!(
+            lex_parse_and_reduce(term, Strategy::NormalOrder, 100),
+            Some(lex_and_parse("x z"))
+        );
+    }
+
+    #[test]
+    fn redex_order() {
+        let term = lex_and_parse("(λx.x x) ((λy.y) z) ((λy.y) z)");
+        assert_eq!(find_redex(&term, Strategy::NormalOrder), Some(vec![]));
+        assert_eq!(find_redex(&term, Strategy::CallByName), Some(vec![]));
+        assert_eq!(find_redex(&term, Strategy::CallByValue), Some(vec![1]));
+        assert_eq!(find_redex(&term, Strategy::ApplicativeOrder), Some(vec![1]));
+        let term = lex_and_parse("(λx.(λy.y) x) ((λy.y) z)");
+        assert_eq!(
+            find_redex(&term, Strategy::ApplicativeOrder),
+            Some(vec![0, 0])
+        );
+        assert_eq!(find_redex(&term, Strategy::CallByValue), Some(vec![1]));
+    }
+
+    #[test]
+    fn full_contracts_every_redex_at_once() {
+        assert_eq!(
+            lex_parse_and_reduce("(λx.x) ((λy.y) z)", Strategy::Full, 1),
+            Some(Expression::Variable("z".into()))
+        );
+        assert_eq!(
+            lex_parse_and_reduce("(λx.x) ((λy.y) z)", Strategy::NormalOrder, 1),
+            None
+        );
+    }
+
+    #[test]
+    fn run_out_of_fuel() {
+        let expression = lex_and_parse(OMEGA);
+        let options = Options {
+            max_steps: Some(5),
+            ..Options::default()
+        };
+        let mut steps = 0;
+        let outcome = reduce(
+            expression.clone(),
+            SpanTree::unknown(&expression),
+            &options,
+            |_, _, _| steps += 1,
+        );
+        assert_eq!(outcome, Outcome::OutOfFuel(expression, 5));
+        assert_eq!(steps, 5);
+    }
+
+    #[test]
+    fn time_out() {
+        let expression = lex_and_parse(OMEGA);
+        let options = Options {
+            max_steps: None,
+            timeout: Some(Duration::from_millis(10)),
+            ..Options::default()
+        };
+        let outcome = reduce(
+            expression.clone(),
+            SpanTree::unknown(&expression),
+            &options,
+            |_, _, _| {},
+        );
+        match outcome {
+            Outcome::Timeout(e, _) => assert_eq!(e, expression),
+            outcome => panic!("expected a timeout, got {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn detect_cycles() {
+        let options = Options {
+            max_steps: Some(100),
+            detect_cycles: true,
+            ..Options::default()
+        };
+        let expression = lex_and_parse(OMEGA);
+        let outcome = reduce(
+            expression.clone(),
+            SpanTree::unknown(&expression),
+            &options,
+            |_, _, _| {},
+        );
+        assert_eq!(outcome, Outcome::Cycle(expression, 1, 1));
+        let expression = lex_and_parse("(λx.(λy.y y) x) (λz.(λy.y y) z)");
+        let outcome = reduce(
+            expression.clone(),
+            SpanTree::unknown(&expression),
+            &options,
+            |_, _, _| {},
+        );
+        match outcome {
+            Outcome::Cycle(_, 2, 2) => {}
+            outcome => panic!("expected a cycle of length 2, got {:?}", outcome),
+        }
+        let expression = lex_and_parse("(λx.x x y) (λx.x x y)");
+        let outcome = reduce(
+            expression.clone(),
+            SpanTree::unknown(&expression),
+            &options,
+            |_, _, _| {},
+        );
+        match outcome {
+            Outcome::OutOfFuel(_, _) => {}
+            outcome => panic!("expected to run out of fuel, got {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn limits_only_stop_terms_with_redexes() {
+        let expression = lex_and_parse("(λx.x) y");
+        let options = Options {
+            max_steps: Some(1),
+            timeout: Some(Duration::from_millis(0)),
+            ..Options::default()
+        };
+        let outcome = reduce(
+            expression.clone(),
+            SpanTree::unknown(&expression),
+            &options,
+            |_, _, _| {},
+        );
+        assert_eq!(outcome, Outcome::Timeout(expression, 0));
+        let expression = lex_and_parse("y");
+        let outcome = reduce(
+            expression.clone(),
+            SpanTree::unknown(&expression),
+            &options,
+            |_, _, _| {},
+        );
+        assert_eq!(outcome, Outcome::NormalForm(expression, 0));
+    }
+
+    #[test]
+    fn enumerate_redexes() {
+        let expression = lex_and_parse("(λx.x x) ((λy.y) z)");
+        assert_eq!(redexes(&expression, StepKind::Beta), vec![vec![], vec![1]]);
+        let expression = lex_and_parse("λa.(λb.b) ((λc.c) a) (λd.e d)");
+        assert_eq!(
+            redexes(&expression, StepKind::Beta),
+            vec![vec![0], vec![0, 1]]
+        );
+        assert_eq!(redexes(&expression, StepKind::Eta), vec![vec![0, 2]]);
+    }
+
+    #[test]
+    fn contract_chosen_redexes() {
+        let expression = lex_and_parse("(λx.x x) ((λy.y) z)");
+        let cases = [(vec![], "(λy.y) z ((λy.y) z)"), (vec![1], "(λx.x x) z")];
+        for (path, expected) in &cases {
+            let contracted = contract_at(expression.clone(), path).unwrap();
+            assert_eq!(contracted, lex_and_parse(expected));
+        }
+        let expression = lex_and_parse("λa.f a");
+        assert_eq!(
+            contract_at(expression.clone(), &[]),
+            Some(lex_and_parse("f"))
+        );
+        for path in &[vec![0], vec![0, 0], vec![1], vec![0, 5]] {
+            assert_eq!(contract_at(expression.clone(), path), None);
+        }
+    }
+
+    /// The normal forms of every term `expr` reduces to, found by contracting every redex
+    /// of every reduct. Reducts that come back up to renaming are only explored once.
+    fn all_normal_forms(expr: Expression) -> Vec<Expression> {
+        let mut seen = HashSet::new();
+        let mut todo = vec![expr];
+        let mut normal_forms = Vec::new();
+        while let Some(expr) = todo.pop() {
+            if !seen.insert(to_de_bruijn(&expr)) {
+                continue;
+            }
+            let next = reducts(&expr);
+            if next.is_empty() {
+                normal_forms.push(expr);
+            }
+            todo.extend(next.into_iter().map(|(_, e)| e));
+            assert!(seen.len() < 10_000, "too many reducts");
+        }
+        normal_forms
+    }
+
+    /// Church–Rosser: however the redexes are picked, a term never gets to two different
+    /// normal forms.
+    #[test]
+    fn every_reduction_path_reaches_the_same_normal_form() {
+        let cases = [
+            ("(λx.x x) ((λy.y) z)", "z z"),
+            ("(λx y.x) ((λa.a) b) ((λc.c c) d)", "b"),
+            (&format!("(λx.y) {}", OMEGA), "y"),
+            ("PLUS 1 2", "3"),
+            ("NOT (AND TRUE (OR FALSE TRUE))", "λt f.f"),
+            ("S K K a", "a"),
+        ];
+        for &(term, expected) in &cases {
+            let expression = lex_parse_and_expand(&format!("{} {}", PRELUDE, term));
+            let normal_forms = all_normal_forms(expression);
+            assert_eq!(normal_forms.len(), 1, "{}", term);
+            assert!(
+                alpha_eq(&normal_forms[0], &lex_and_parse(expected)),
+                "{}",
+                term
+            );
+        }
+    }
+}
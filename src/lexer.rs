@@ -1,5 +1,7 @@
 use common::{Direction, Sink, Source, State};
+use encoding::MAX_LITERAL;
 use std::fmt;
+use std::mem;
 
 #[derive(PartialEq)]
 pub struct Token {
@@ -30,31 +32,51 @@ impl fmt::Debug for Token {
     }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+/// Points compare by row first and then by column, in the order they are in the source.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
 pub struct Point {
     pub row: usize,
     pub column: usize,
 }
 
 impl Point {
-    fn new(row: usize, column: usize) -> Self {
+    pub fn new(row: usize, column: usize) -> Self {
         Point { row, column }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Position {
-    starting_point: Point,
-    ending_point: Point,
+    pub starting_point: Point,
+    pub ending_point: Point,
 }
 
 impl Position {
-    fn new(starting_point: Point, ending_point: Point) -> Self {
+    pub fn new(starting_point: Point, ending_point: Point) -> Self {
         Position {
             starting_point,
             ending_point,
         }
     }
+
+    /// Whether `other` lies within this position.
+    pub fn contains(&self, other: Position) -> bool {
+        self.starting_point <= other.starting_point && other.ending_point <= self.ending_point
+    }
+}
+
+/// Shown the way people count: rows and columns start from one.
+impl fmt::Display for Position {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "{}:{}-{}:{}",
+            self.starting_point.row + 1,
+            self.starting_point.column + 1,
+            self.ending_point.row + 1,
+            self.ending_point.column + 1,
+        )
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -63,6 +85,34 @@ pub enum TokenType {
     Dot,
     Bracket(Direction),
     Identifier(String),
+    /// The `=` between the name and the expression of a definition.
+    Equals,
+    /// The `;` that ends a definition.
+    Separator,
+    /// The keyword `let` of `let x = M in N`.
+    Let,
+    /// The keyword `in` of `let x = M in N`.
+    In,
+    /// A word made of nothing but the digits 0 to 9.
+    Number(usize),
+    /// The text of a `-- line comment` or a `{- block comment -}` without the delimiters.
+    /// Only produced if the lexer was asked to keep comments.
+    Comment(String),
+}
+
+impl TokenType {
+    /// Keywords and numbers are lexed like identifiers and told apart afterwards.
+    fn from_word(word: String) -> Result<TokenType, String> {
+        match word.as_str() {
+            "let" => Ok(TokenType::Let),
+            "in" => Ok(TokenType::In),
+            _ if word.chars().all(|c| c.is_ascii_digit()) => match word.parse() {
+                Ok(n) if n <= MAX_LITERAL => Ok(TokenType::Number(n)),
+                _ => Err(format!("The number {} is too large", word)),
+            },
+            _ => Ok(TokenType::Identifier(word)),
+        }
+    }
 }
 
 pub struct Lexer<O>
@@ -74,6 +124,13 @@ where
     starting_point: Option<Point>,
     row: usize,
     column: usize,
+    keep_comments: bool,
+    /// A `-` or a `{` that might start a comment and where it was.
+    comment_start: Option<(char, Point)>,
+    /// The text of the comment that is being lexed, if any.
+    comment: Option<String>,
+    /// How many block comments are open. Block comments nest.
+    comment_depth: usize,
 }
 
 impl<O> Lexer<O>
@@ -87,9 +144,20 @@ where
             starting_point: None,
             row: 0,
             column: 0,
+            keep_comments: false,
+            comment_start: None,
+            comment: None,
+            comment_depth: 0,
         }
     }
 
+    /// Comments are skipped by default. If they are kept, each one becomes a
+    /// `TokenType::Comment` for tools that care about them.
+    pub fn keep_comments(mut self, keep_comments: bool) -> Self {
+        self.keep_comments = keep_comments;
+        self
+    }
+
     pub fn run<I>(&mut self, mut char_source: I)
     where
         I: Source<char>,
@@ -106,26 +174,38 @@ where
             }
         }
         if !self.buffer.is_empty() {
-            let token_type = TokenType::Identifier(self.buffer.clone());
-            self.buffer.clear();
-            let mut position = self.get_current_position();
-            position.ending_point.column -= 1;
-            self.starting_point = None;
-            let token = Token::new(token_type, position);
-            self.token_sink.put(Ok(token));
+            self.end_word();
+        }
+        if self.comment_depth > 0 {
+            let (_, starting_point) = self.comment_start.take().unwrap();
+            let ending_point = Point::new(starting_point.row, starting_point.column + 1);
+            self.comment = None;
+            self.comment_depth = 0;
+            let error = LexError::UnterminatedCommentError(Position::new(
+                starting_point,
+                ending_point,
+            ));
+            self.token_sink.put(Err(error));
+        } else if self.comment.is_some() {
+            let ending_point = Point::new(self.row, self.column - 1);
+            self.end_comment(ending_point);
+        } else {
+            self.invalid_comment_start();
         }
     }
 
     fn normal(&mut self, c: char) -> State<Lexer<O>, char> {
         use self::TokenType::*;
-        use Direction::*;
+        use common::Direction::*;
         match c {
-            '\\' | 'λ' | '(' | ')' | '.' => {
+            '\\' | 'λ' | '(' | ')' | '.' | '=' | ';' => {
                 let token_type = match c {
                     '\\' | 'λ' => Lambda,
                     '(' => Bracket(Left),
                     ')' => Bracket(Right),
                     '.' => Dot,
+                    '=' => Equals,
+                    ';' => Separator,
                     _ => unreachable!(),
                 };
                 let position = self.get_current_position();
@@ -141,6 +221,10 @@ where
                 self.buffer.push(c);
                 State(Self::identifier)
             }
+            '-' | '{' => {
+                self.comment_start = Some((c, Point::new(self.row, self.column)));
+                State(Self::comment_start)
+            }
             w if w.is_whitespace() => State(Self::normal),
 
             _ => {
@@ -155,15 +239,9 @@ where
     fn identifier(&mut self, c: char) -> State<Lexer<O>, char> {
         match c {
             c if c.is_whitespace() || c == '\\' || c == 'λ' || c == '(' || c == ')'
-                || c == '.' =>
+                || c == '.' || c == '=' || c == ';' || c == '-' || c == '{' =>
             {
-                let token_type = TokenType::Identifier(self.buffer.clone());
-                self.buffer.clear();
-                let mut position = self.get_current_position();
-                position.ending_point.column -= 1;
-                self.starting_point = None;
-                let token = Token::new(token_type, position);
-                self.token_sink.put(Ok(token));
+                self.end_word();
                 self.normal(c)
             }
             c if c.is_alphanumeric() => {
@@ -182,6 +260,130 @@ where
         }
     }
 
+    /// Turns the word in the buffer into a token. The word ended at the previous character.
+    fn end_word(&mut self) {
+        let word = mem::take(&mut self.buffer);
+        let mut position = self.get_current_position();
+        position.ending_point.column -= 1;
+        self.starting_point = None;
+        match TokenType::from_word(word) {
+            Ok(token_type) => {
+                let token = Token::new(token_type, position);
+                self.token_sink.put(Ok(token));
+            }
+            Err(message) => {
+                let error = LexError::IdentifierError(message, position);
+                self.token_sink.put(Err(error));
+            }
+        }
+    }
+
+    /// After a `-` or a `{`. Only `--` and `{-` start a comment.
+    fn comment_start(&mut self, c: char) -> State<Lexer<O>, char> {
+        match (self.comment_start, c) {
+            (Some(('-', _)), '-') => {
+                self.comment = Some(String::new());
+                State(Self::line_comment)
+            }
+            (Some(('{', _)), '-') => {
+                self.comment = Some(String::new());
+                self.comment_depth = 1;
+                State(Self::block_comment)
+            }
+            _ => {
+                self.invalid_comment_start();
+                self.normal(c)
+            }
+        }
+    }
+
+    /// A `-` or a `{` that did not start a comment is not valid anywhere.
+    fn invalid_comment_start(&mut self) {
+        if let Some((c, point)) = self.comment_start.take() {
+            let error = LexError::InvalidCharacterError(c, Position::new(point, point));
+            self.token_sink.put(Err(error));
+        }
+    }
+
+    fn line_comment(&mut self, c: char) -> State<Lexer<O>, char> {
+        match c {
+            '\n' => {
+                let ending_point = Point::new(self.row, self.column.saturating_sub(1));
+                self.end_comment(ending_point);
+                State(Self::normal)
+            }
+            _ => {
+                self.push_to_comment(c);
+                State(Self::line_comment)
+            }
+        }
+    }
+
+    fn block_comment(&mut self, c: char) -> State<Lexer<O>, char> {
+        match c {
+            '-' => State(Self::block_comment_dash),
+            '{' => State(Self::block_comment_brace),
+            _ => {
+                self.push_to_comment(c);
+                State(Self::block_comment)
+            }
+        }
+    }
+
+    /// After a `-` in a block comment. `-}` closes the innermost block comment.
+    fn block_comment_dash(&mut self, c: char) -> State<Lexer<O>, char> {
+        match c {
+            '}' => {
+                self.comment_depth -= 1;
+                if self.comment_depth == 0 {
+                    let ending_point = Point::new(self.row, self.column);
+                    self.end_comment(ending_point);
+                    State(Self::normal)
+                } else {
+                    self.push_to_comment('-');
+                    self.push_to_comment('}');
+                    State(Self::block_comment)
+                }
+            }
+            _ => {
+                self.push_to_comment('-');
+                self.block_comment(c)
+            }
+        }
+    }
+
+    /// After a `{` in a block comment. `{-` opens a nested block comment.
+    fn block_comment_brace(&mut self, c: char) -> State<Lexer<O>, char> {
+        match c {
+            '-' => {
+                self.comment_depth += 1;
+                self.push_to_comment('{');
+                self.push_to_comment('-');
+                State(Self::block_comment)
+            }
+            _ => {
+                self.push_to_comment('{');
+                self.block_comment(c)
+            }
+        }
+    }
+
+    fn push_to_comment(&mut self, c: char) {
+        if let Some(comment) = self.comment.as_mut() {
+            comment.push(c);
+        }
+    }
+
+    fn end_comment(&mut self, ending_point: Point) {
+        let comment = self.comment.take().unwrap_or_default();
+        let (_, starting_point) = self.comment_start.take().unwrap();
+        if self.keep_comments {
+            let position = Position::new(starting_point, ending_point);
+            let token = Token::new(TokenType::Comment(comment), position);
+            self.token_sink.put(Ok(token));
+        }
+    }
+
     fn get_current_position(&mut self) -> Position {
         let starting_point = match self.starting_point {
             Some(point) => point,
@@ -205,6 +407,8 @@ where
 pub enum LexError {
     IdentifierError(String, Position),
     InvalidCharacterError(char, Position),
+    /// A block comment that is still open at the end of the input. Points at its `{-`.
+    UnterminatedCommentError(Position),
 }
 
 #[cfg(test)]
@@ -420,4 +624,127 @@ mod test {
         );
     }
 
+    #[test]
+    fn lex_definitions() {
+        use self::TokenType::*;
+        let expected = construct_expected!(
+            Identifier("I".into()), (0, 0), (0, 0);
+            Equals, (0, 1), (0, 1);
+            Lambda, (0, 2), (0, 2);
+            Identifier("x".into()), (0, 3), (0, 3);
+            Dot, (0, 4), (0, 4);
+            Identifier("x".into()), (0, 5), (0, 5);
+            Separator, (0, 6), (0, 6);
+            Identifier("I".into()), (1, 0), (1, 0);
+            Identifier("y".into()), (1, 2), (1, 2);
+        );
+        lex_and_assert("I=λx.x;\nI y", &expected);
+    }
+
+    #[test]
+    fn lex_comments() {
+        use self::TokenType::*;
+        let source = "x -- a comment\n{- block {- nested -} -} y-- trailing";
+        let expected = construct_expected!(
+            Identifier("x".into()), (0, 0), (0, 0);
+            Identifier("y".into()), (1, 25), (1, 25);
+        );
+        lex_and_assert(source, &expected);
+
+        let expected = construct_expected!(
+            Identifier("x".into()), (0, 0), (0, 0);
+            Comment(" a comment".into()), (0, 2), (0, 13);
+            Comment(" block {- nested -} ".into()), (1, 0), (1, 23);
+            Identifier("y".into()), (1, 25), (1, 25);
+            Comment(" trailing".into()), (1, 26), (1, 36);
+        );
+        let mut sink = VecDeque::new();
+        {
+            let mut lexer = Lexer::new(&mut sink).keep_comments(true);
+            lexer.run(source.to_string());
+        }
+        assert_eq!(sink, expected);
+    }
+
+    #[test]
+    fn lex_broken_comments() {
+        let position = |from: (usize, usize), to: (usize, usize)| {
+            Position::new(Point::new(from.0, from.1), Point::new(to.0, to.1))
+        };
+        let mut expected = construct_expected!(
+            TokenType::Identifier("x".into()), (0, 0), (0, 0);
+        );
+        expected.push_back(Err(LexError::InvalidCharacterError(
+            '-',
+            position((0, 2), (0, 2)),
+        )));
+        expected.push_back(Ok(Token::new(
+            TokenType::Identifier("y".into()),
+            position((0, 4), (0, 4)),
+        )));
+        expected.push_back(Err(LexError::InvalidCharacterError(
+            '{',
+            position((0, 6), (0, 6)),
+        )));
+        expected.push_back(Ok(Token::new(
+            TokenType::Identifier("z".into()),
+            position((0, 7), (0, 7)),
+        )));
+        expected.push_back(Err(LexError::UnterminatedCommentError(position(
+            (0, 9),
+            (0, 10),
+        ))));
+        lex_and_assert("x - y {z {- open\n", &expected);
+    }
+
+    #[test]
+    fn lex_numbers() {
+        use self::TokenType::*;
+        let expected = construct_expected!(
+            Number(123), (0, 0), (0, 2);
+            Identifier("x1".into()), (0, 4), (0, 5);
+            Identifier("1x".into()), (0, 7), (0, 8);
+            Bracket(Direction::Left), (0, 10), (0, 10);
+            Number(0), (0, 11), (0, 11);
+            Bracket(Direction::Right), (0, 12), (0, 12);
+        );
+        lex_and_assert("123 x1 1x (0)", &expected);
+    }
+
+    #[test]
+    fn lex_too_large_number() {
+        let mut expected = VecDeque::new();
+        expected.push_back(Err(LexError::IdentifierError(
+            "The number 99999999999999999999999 is too large".into(),
+            Position::new(Point::new(0, 0), Point::new(0, 22)),
+        )));
+        lex_and_assert("99999999999999999999999", &expected);
+    }
+
+    #[test]
+    fn lex_number_larger_than_any_numeral() {
+        let mut expected = VecDeque::new();
+        expected.push_back(Err(LexError::IdentifierError(
+            "The number 100000 is too large".into(),
+            Position::new(Point::new(0, 0), Point::new(0, 5)),
+        )));
+        lex_and_assert("100000", &expected);
+    }
+
+    #[test]
+    fn lex_let_in() {
+        use self::TokenType::*;
+        let expected = construct_expected!(
+            Let, (0, 0), (0, 2);
+            Identifier("x".into()), (0, 4), (0, 4);
+            Equals, (0, 6), (0, 6);
+            Identifier("y".into()), (0, 8), (0, 8);
+            In, (0, 10), (0, 11);
+            Identifier("inx".into()), (0, 13), (0, 15);
+            Identifier("letx".into()), (0, 17), (0, 20);
+            In, (0, 22), (0, 23);
+        );
+        lex_and_assert("let x = y in inx letx in", &expected);
+    }
+
 }
@@ -0,0 +1,18 @@
+pub mod alpha;
+pub mod cli;
+pub mod common;
+pub mod debugger;
+pub mod debruijn;
+pub mod diagnostics;
+pub mod encoding;
+pub mod file;
+pub mod graph;
+pub mod interpreter;
+pub mod lexer;
+pub mod parser;
+pub mod pretty;
+pub mod readback;
+pub mod repl;
+pub mod span;
+pub mod tree;
+pub mod types;
@@ -1,16 +1,10 @@
-use common::Direction;
-use lexer::Lexer;
-use parser::Parser;
-use interpreter::interpret;
+extern crate lamppa;
 
-mod common;
-mod file;
-mod lexer;
-mod parser;
-mod interpreter;
+use lamppa::cli;
+use std::env;
+use std::process;
 
 fn main() {
-    thread::spawn(move || {
-        Lexer::new();
-    });
+    let args: Vec<String> = env::args().skip(1).collect();
+    process::exit(cli::main(&args));
 }
@@ -1,5 +1,8 @@
-use common::{Direction, Sink, Source, State};
+use common::{Direction, Source, State};
+use encoding::NumeralEncoding;
 use lexer::{LexError, Position, Token, TokenType};
+use span::{span_between, SpanTree};
+use std::mem;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expression {
@@ -8,69 +11,276 @@ pub enum Expression {
     Variable(String),
 }
 
+/// Everything that can go wrong while turning a stream of tokens into an `Expression`.
+/// Every error except `EmptyExpression` carries the `Position` of the offending token.
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    /// A closing bracket that has no matching opening bracket.
+    UnexpectedClosingBracket(Position),
+    /// A dot that does not follow a lambda and its identifiers.
+    DotWithoutLambda(Position),
+    /// A lambda that is not followed by an identifier.
+    MissingIdentifierAfterLambda(Position),
+    /// Something other than a dot or an identifier after a lambda and an identifier.
+    MissingDot(Position),
+    /// A pair of brackets with nothing in between. Spans both brackets.
+    EmptyParenthesis(Position),
+    /// A lambda abstraction without a body. Points at the lambda and the bound identifier.
+    EmptyLambdaBody(Position),
+    /// An opening bracket that is never closed.
+    UnclosedBracket(Position),
+    /// The token stream contained no expression at all.
+    EmptyExpression,
+    /// An `=` that does not follow the name of a definition.
+    UnexpectedEquals(Position),
+    /// A definition without an expression. Points at the name being defined.
+    EmptyDefinition(Position),
+    /// An expression without a name that is not the last statement. Only the main expression
+    /// at the very end may go without a name.
+    MisplacedExpression(Position),
+    /// A definition where only a single expression was expected. Points at the name.
+    UnexpectedDefinition(Position),
+    /// A `let` that is not followed by an identifier.
+    MissingIdentifierAfterLet(Position),
+    /// Something other than an `=` after `let` and an identifier.
+    MissingEquals(Position),
+    /// A `let` whose value ends without an `in`. Points at `let` and the identifier.
+    MissingIn(Position),
+    /// An `in` without a `let`.
+    UnexpectedIn(Position),
+    /// `let x = in ...`. Points at `let` and the identifier.
+    EmptyLetValue(Position),
+    /// `let x = M in` with nothing after it. Points at `let` and the identifier.
+    EmptyLetBody(Position),
+    LexError(LexError),
+}
+
+/// `name = expression;`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Definition {
+    pub name: String,
+    /// Where the name is in the source.
+    pub position: Position,
+    pub expression: Expression,
+    pub spans: SpanTree,
+}
+
+/// A sequence of definitions followed by an optional main expression:
+///
+/// ```text
+/// TRUE = λt f.t;
+/// FALSE = λt f.f;
+/// TRUE FALSE TRUE
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Program {
+    /// In the order they appear in the source.
+    pub definitions: Vec<Definition>,
+    pub main: Option<Spanned>,
+}
+
+/// An expression together with the source positions of all of its parts.
+pub type Spanned = (Expression, SpanTree);
+
 enum Incomplete {
-    Expressions(Vec<Expression>),
-    Lambda(String, Vec<Expression>),
+    /// The position is the one of the opening bracket. The outermost expression has none.
+    Expressions(Option<Position>, Vec<Spanned>),
+    /// The position is where the lambda abstraction starts. For `λx y.M` the abstraction
+    /// over `x` starts at the lambda and the one over `y` at `y`.
+    Lambda(String, Position, Vec<Spanned>),
+    /// The value of `let x = M in N` while `M` is being parsed. The position spans `let x`.
+    LetValue(String, Position, Vec<Spanned>),
+    /// The body of `let x = M in N` while `N` is being parsed. Like the body of a lambda it
+    /// extends as far to the right as possible.
+    LetBody(String, Position, Spanned, Vec<Spanned>),
 }
 
 pub struct Parser {
     parse_stack: Vec<Incomplete>,
+    numeral_encoding: NumeralEncoding,
+    lambda_position: Option<Position>,
+    let_position: Option<Position>,
+    /// The identifier after `let` and the position of both while waiting for the `=`.
+    let_name: Option<(String, Position)>,
+    /// The name and its position if the current statement is a definition.
+    definition: Option<(String, Position)>,
+    definitions: Vec<Definition>,
+    main: Option<Spanned>,
+    errors: Vec<ParseError>,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Parser {
-    fn new() -> Parser {
+    pub fn new() -> Parser {
+        Parser::with_encoding(NumeralEncoding::default())
+    }
+
+    /// A parser that turns numeric literals into numerals of the given encoding.
+    pub fn with_encoding(numeral_encoding: NumeralEncoding) -> Parser {
         Parser {
-            parse_stack: vec![Incomplete::Expressions(Vec::new())],
+            parse_stack: vec![Incomplete::Expressions(None, Vec::new())],
+            numeral_encoding,
+            lambda_position: None,
+            let_position: None,
+            let_name: None,
+            definition: None,
+            definitions: Vec::new(),
+            main: None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Parses the whole token stream. Instead of giving up on the first problem the parser
+    /// recovers and keeps going, so every lexing and parsing error is reported at once.
+    pub fn run<I>(&mut self, token_source: I) -> Result<Expression, Vec<ParseError>>
+    where
+        I: Source<Result<Token, LexError>>,
+    {
+        self.run_with_spans(token_source).map(|(e, _)| e)
+    }
+
+    /// Like `run`, but also returns where in the source every part of the expression came from.
+    pub fn run_with_spans<I>(&mut self, token_source: I) -> Result<Spanned, Vec<ParseError>>
+    where
+        I: Source<Result<Token, LexError>>,
+    {
+        let program = self.run_program(token_source)?;
+        let mut errors: Vec<ParseError> = program
+            .definitions
+            .iter()
+            .map(|d| ParseError::UnexpectedDefinition(d.position))
+            .collect();
+        match program.main {
+            Some(main) if errors.is_empty() => Ok(main),
+            Some(_) => Err(errors),
+            None => {
+                errors.push(ParseError::EmptyExpression);
+                Err(errors)
+            }
         }
     }
 
-    fn run<I>(&mut self, mut token_source: I) -> Expression
+    /// Parses a whole program of `;` separated definitions and a main expression. After an
+    /// error the parser picks up again at the next `;` at the latest.
+    pub fn run_program<I>(&mut self, mut token_source: I) -> Result<Program, Vec<ParseError>>
     where
         I: Source<Result<Token, LexError>>,
     {
         let mut state = State(Self::normal);
-        let mut error = false;
         while let Some(r) = token_source.take() {
             match r {
+                Ok(Token {
+                    token_type: TokenType::Comment(_),
+                    ..
+                }) => {}
                 Ok(t) => {
                     state = state(self, t);
                 }
                 Err(err) => {
-                    println!("{:?}", err);
-                    error = true;
-                    break;
+                    self.errors.push(ParseError::LexError(err));
                 }
             }
         }
-        if error {
-            self.print_errors(token_source);
+        if let Some(position) = self.lambda_position.take() {
+            self.errors
+                .push(ParseError::MissingIdentifierAfterLambda(position));
+        }
+        if let Some(position) = self.let_position.take() {
+            self.errors
+                .push(ParseError::MissingIdentifierAfterLet(position));
+        }
+        if let Some((_, position)) = self.let_name.take() {
+            self.errors.push(ParseError::MissingEquals(position));
+        }
+        self.end_statement();
+        let program = Program {
+            definitions: mem::take(&mut self.definitions),
+            main: self.main.take(),
+        };
+        if self.errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(mem::take(&mut self.errors))
+        }
+    }
+
+    /// Finishes the current statement, which is either a definition or the main expression.
+    fn end_statement(&mut self) {
+        let expression = self.construct_expression();
+        if expression.is_some() || self.definition.is_some() {
+            if let Some((_, spans)) = self.main.take() {
+                let position = spans.position.unwrap();
+                self.errors.push(ParseError::MisplacedExpression(position));
+            }
+        }
+        match (self.definition.take(), expression) {
+            (Some((name, position)), Some((expression, spans))) => {
+                self.definitions.push(Definition {
+                    name,
+                    position,
+                    expression,
+                    spans,
+                });
+            }
+            (Some((_, position)), None) => {
+                self.errors.push(ParseError::EmptyDefinition(position));
+            }
+            (None, Some(main)) => self.main = Some(main),
+            (None, None) => {}
         }
-        self.construct_expression()
     }
 
-    fn construct_expression(&mut self) -> Expression {
+    /// Turns the current statement into a definition if all that has been parsed of it so far
+    /// is a single identifier.
+    fn start_definition(&mut self, equals_position: Position) {
+        let name = match self.parse_stack.as_mut_slice() {
+            [Incomplete::Expressions(None, v)] if v.len() == 1 && self.definition.is_none() => {
+                match v.pop() {
+                    Some((Expression::Variable(name), spans)) => Some((name, spans)),
+                    Some(other) => {
+                        v.push(other);
+                        None
+                    }
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+        match name {
+            Some((name, spans)) => self.definition = Some((name, spans.position.unwrap())),
+            None => self
+                .errors
+                .push(ParseError::UnexpectedEquals(equals_position)),
+        }
+    }
+
+    /// Closes everything that is still open. Missing closing brackets are reported and
+    /// then treated as if they were there.
+    fn construct_expression(&mut self) -> Option<Spanned> {
         loop {
             if self.parse_stack.len() == 1 {
-                if let Incomplete::Expressions(v) = self.parse_stack.pop().unwrap() {
-                    match Self::convert_to_expression(v) {
-                        Some(e) => return e,
-                        None => {
-                            println!("expression was empty");
-                            panic!();
-                        }
-                    }
+                if let Incomplete::Expressions(_, v) = self.parse_stack.pop().unwrap() {
+                    self.parse_stack
+                        .push(Incomplete::Expressions(None, Vec::new()));
+                    return Self::convert_to_expression(v);
                 } else {
                     unreachable!();
                 }
             } else if self.parse_stack.len() > 1 {
                 match self.parse_stack.pop().unwrap() {
-                    Incomplete::Lambda(i, mut v) => {
-                        self.bubble_up_expression(Some(i), v);
-                    }
-                    Incomplete::Expressions(_) => {
-                        println!("a closing bracket is missing");
-                        panic!();
+                    Incomplete::Expressions(position, v) => {
+                        self.errors
+                            .push(ParseError::UnclosedBracket(position.unwrap()));
+                        if let Some(e) = Self::convert_to_expression(v) {
+                            self.push_expression(e);
+                        }
                     }
+                    incomplete => self.bubble_up(incomplete),
                 }
             } else {
                 unreachable!();
@@ -78,154 +288,336 @@ impl Parser {
         }
     }
 
-    fn print_errors<I>(&mut self, mut token_source: I)
-    where
-        I: Source<Result<Token, LexError>>,
-    {
-        while let Some(r) = token_source.take() {
-            match r {
-                Err(err) => {
-                    println!("{:?}", err);
-                }
-                _ => {}
-            }
-        }
-        panic!("Lexing failed. Aborting");
-    }
-
     fn normal(&mut self, t: Token) -> State<Parser, Token> {
         match t.token_type {
             TokenType::Bracket(Direction::Left) => {
-                let incomplete = Incomplete::Expressions(Vec::new());
+                let incomplete = Incomplete::Expressions(Some(t.position), Vec::new());
                 self.parse_stack.push(incomplete);
-                State(Self::normal)
             }
             TokenType::Bracket(Direction::Right) => {
-                loop {
-                    if self.parse_stack.len() > 1 {
-                        match self.parse_stack.pop().unwrap() {
-                            Incomplete::Expressions(mut v) => {
-                                self.bubble_up_expression(None, v);
-                                break;
-                            }
-                            Incomplete::Lambda(i, mut v) => {
-                                self.bubble_up_expression(Some(i), v);
-                            }
-                        }
-                    } else {
-                        println!("Unexpected closing bracket at {:?}", t.position);
-                        panic!();
-                    }
-                }
-                State(Self::normal)
+                self.close_bracket(t.position);
             }
             TokenType::Dot => {
-                println!("A dot was found without a lambda at {:?}", t.position);
-                panic!();
+                self.errors.push(ParseError::DotWithoutLambda(t.position));
             }
             TokenType::Identifier(s) => {
-                let expression = Expression::Variable(s);
-                match self.parse_stack.pop() {
-                    Some(mut incomplete) => {
-                        match &mut incomplete {
-                            Incomplete::Expressions(v) => v.push(expression),
-                            Incomplete::Lambda(_, v) => v.push(expression),
+                let spans = SpanTree::new(Some(t.position), Vec::new());
+                self.push_expression((Expression::Variable(s), spans));
+            }
+            TokenType::Number(n) if n > self.numeral_encoding.max_literal() => {
+                self.errors
+                    .push(ParseError::LexError(LexError::IdentifierError(
+                        format!("The number {} is too large", n),
+                        t.position,
+                    )));
+                let spans = SpanTree::new(Some(t.position), Vec::new());
+                self.push_expression((Expression::Variable(n.to_string()), spans));
+            }
+            TokenType::Number(n) => {
+                let expression = self.numeral_encoding.encode(n);
+                let spans = SpanTree::filled(&expression, Some(t.position));
+                self.push_expression((expression, spans));
+            }
+            TokenType::Lambda => {
+                self.lambda_position = Some(t.position);
+                return State(Self::lambda);
+            }
+            TokenType::Equals => {
+                self.start_definition(t.position);
+            }
+            TokenType::Separator => {
+                self.end_statement();
+            }
+            TokenType::Let => {
+                self.let_position = Some(t.position);
+                return State(Self::let_binding);
+            }
+            TokenType::In => {
+                self.start_let_body(t.position);
+            }
+            TokenType::Comment(_) => {}
+        }
+        State(Self::normal)
+    }
+
+    /// A stray closing bracket is reported and skipped without closing any open lambdas.
+    fn close_bracket(&mut self, closing_position: Position) {
+        let has_open_bracket = self
+            .parse_stack
+            .iter()
+            .skip(1)
+            .any(|incomplete| matches!(incomplete, Incomplete::Expressions(..)));
+        if !has_open_bracket {
+            self.errors
+                .push(ParseError::UnexpectedClosingBracket(closing_position));
+            return;
+        }
+        loop {
+            match self.parse_stack.pop().unwrap() {
+                Incomplete::Expressions(opening_position, v) => {
+                    match Self::convert_to_expression(v) {
+                        Some(e) => self.push_expression(e),
+                        None => {
+                            let position = Position::new(
+                                opening_position.unwrap().starting_point,
+                                closing_position.ending_point,
+                            );
+                            self.errors.push(ParseError::EmptyParenthesis(position));
                         }
-                        self.parse_stack.push(incomplete);
                     }
-                    None => unreachable!(),
+                    return;
                 }
-                State(Self::normal)
+                incomplete => self.bubble_up(incomplete),
             }
-            TokenType::Lambda => State(Self::lambda),
         }
     }
 
-    fn bubble_up_expression(&mut self, lambda_identifier: Option<String>, mut v: Vec<Expression>) {
-        let inner_expression = match Self::convert_to_expression(v) {
-            Some(e) => e,
-            None => {
-                println!("an expression in parenthesis was empty.");
-                panic!();
+    /// Ends the value of the innermost `let` and starts its body. Open lambdas and lets in the
+    /// value are closed, but an `in` never closes a bracket.
+    fn start_let_body(&mut self, in_position: Position) {
+        let has_open_let = self
+            .parse_stack
+            .iter()
+            .rev()
+            .take_while(|incomplete| !matches!(incomplete, Incomplete::Expressions(..)))
+            .any(|incomplete| matches!(incomplete, Incomplete::LetValue(..)));
+        if !has_open_let {
+            self.errors.push(ParseError::UnexpectedIn(in_position));
+            return;
+        }
+        loop {
+            match self.parse_stack.pop().unwrap() {
+                Incomplete::LetValue(name, position, v) => {
+                    match Self::convert_to_expression(v) {
+                        Some(value) => self.parse_stack.push(Incomplete::LetBody(
+                            name,
+                            position,
+                            value,
+                            Vec::new(),
+                        )),
+                        None => self.errors.push(ParseError::EmptyLetValue(position)),
+                    }
+                    return;
+                }
+                incomplete => self.bubble_up(incomplete),
             }
-        };
-        let expression = match lambda_identifier {
-            Some(i) => Expression::Lambda(i, Box::new(inner_expression)),
-            None => inner_expression,
-        };
-        let mut incomplete = self.parse_stack.pop().unwrap();
-        match &mut incomplete {
-            Incomplete::Expressions(e) => e.push(expression),
-            Incomplete::Lambda(_, e) => e.push(expression),
         }
-        self.parse_stack.push(incomplete);
     }
 
-    fn convert_to_expression(mut v: Vec<Expression>) -> Option<Expression> {
+    /// Closes a lambda or a let because whatever surrounds it ends.
+    fn bubble_up(&mut self, incomplete: Incomplete) {
+        match incomplete {
+            Incomplete::Lambda(i, position, v) => self.bubble_up_lambda(i, position, v),
+            Incomplete::LetValue(_, position, v) => {
+                self.errors.push(ParseError::MissingIn(position));
+                if let Some(e) = Self::convert_to_expression(v) {
+                    self.push_expression(e);
+                }
+            }
+            Incomplete::LetBody(i, position, value, v) => self.bubble_up_let(i, position, value, v),
+            Incomplete::Expressions(..) => unreachable!(),
+        }
+    }
+
+    /// A lambda without a body is reported and dropped.
+    fn bubble_up_lambda(&mut self, identifier: String, position: Position, v: Vec<Spanned>) {
+        match Self::convert_to_expression(v) {
+            Some((e, spans)) => {
+                let lambda_position = span_between(Some(position), spans.position);
+                self.push_expression((
+                    Expression::Lambda(identifier, Box::new(e)),
+                    SpanTree::new(lambda_position, vec![spans]),
+                ));
+            }
+            None => self.errors.push(ParseError::EmptyLambdaBody(position)),
+        }
+    }
+
+    /// `let x = M in N` is sugar for `(λx.N) M`.
+    fn bubble_up_let(
+        &mut self,
+        identifier: String,
+        position: Position,
+        value: Spanned,
+        v: Vec<Spanned>,
+    ) {
+        let (value, value_spans) = value;
+        match Self::convert_to_expression(v) {
+            Some((body, body_spans)) => {
+                let let_position = span_between(Some(position), body_spans.position);
+                let lambda = Expression::Lambda(identifier, Box::new(body));
+                let lambda_spans = SpanTree::new(let_position, vec![body_spans]);
+                self.push_expression((
+                    Expression::Application(vec![lambda, value]),
+                    SpanTree::new(let_position, vec![lambda_spans, value_spans]),
+                ));
+            }
+            None => self.errors.push(ParseError::EmptyLetBody(position)),
+        }
+    }
+
+    fn push_expression(&mut self, expression: Spanned) {
+        match self.parse_stack.last_mut() {
+            Some(Incomplete::Expressions(_, v)) => v.push(expression),
+            Some(Incomplete::Lambda(_, _, v)) => v.push(expression),
+            Some(Incomplete::LetValue(_, _, v)) => v.push(expression),
+            Some(Incomplete::LetBody(_, _, _, v)) => v.push(expression),
+            None => unreachable!(),
+        }
+    }
+
+    fn convert_to_expression(mut v: Vec<Spanned>) -> Option<Spanned> {
         if v.len() > 1 {
-            Some(Expression::Application(v))
-        } else if let Some(e) = v.pop() {
-            Some(e)
+            let position = span_between(v[0].1.position, v.last().and_then(|(_, s)| s.position));
+            let (expressions, spans) = v.into_iter().unzip();
+            Some((
+                Expression::Application(expressions),
+                SpanTree::new(position, spans),
+            ))
         } else {
-            None
+            v.pop()
         }
     }
 
+    /// If the identifier is missing the lambda is dropped and the token is parsed as if the
+    /// lambda was never there. A dot right after the lambda is swallowed with it. A number is
+    /// reported but then bound like an identifier would be.
     fn lambda(&mut self, t: Token) -> State<Parser, Token> {
+        let lambda_position = self.lambda_position.take();
         match t.token_type {
             TokenType::Identifier(s) => {
-                self.parse_stack.push(Incomplete::Lambda(s, Vec::new()));
+                let position = span_between(lambda_position, Some(t.position)).unwrap();
+                self.parse_stack
+                    .push(Incomplete::Lambda(s, position, Vec::new()));
+                State(Self::expect_dot_or_identifier)
+            }
+            TokenType::Number(n) => {
+                self.errors
+                    .push(ParseError::MissingIdentifierAfterLambda(t.position));
+                let position = span_between(lambda_position, Some(t.position)).unwrap();
+                self.parse_stack
+                    .push(Incomplete::Lambda(n.to_string(), position, Vec::new()));
                 State(Self::expect_dot_or_identifier)
             }
+            TokenType::Dot => {
+                self.errors
+                    .push(ParseError::MissingIdentifierAfterLambda(t.position));
+                State(Self::normal)
+            }
             _ => {
-                println!("an identifier was expected after a lamda");
-                println!("found {:?} instead.", t);
-                panic!();
+                self.errors
+                    .push(ParseError::MissingIdentifierAfterLambda(t.position));
+                self.normal(t)
             }
         }
     }
 
+    /// If the identifier after `let` is missing the `let` is dropped, together with the `=` if
+    /// that comes next.
+    fn let_binding(&mut self, t: Token) -> State<Parser, Token> {
+        let let_position = self.let_position.take();
+        match t.token_type {
+            TokenType::Identifier(s) => {
+                let position = span_between(let_position, Some(t.position)).unwrap();
+                self.let_name = Some((s, position));
+                State(Self::let_equals)
+            }
+            TokenType::Equals => {
+                self.errors
+                    .push(ParseError::MissingIdentifierAfterLet(t.position));
+                State(Self::normal)
+            }
+            _ => {
+                self.errors
+                    .push(ParseError::MissingIdentifierAfterLet(t.position));
+                self.normal(t)
+            }
+        }
+    }
+
+    /// If the `=` is missing the parser pretends that it was there.
+    fn let_equals(&mut self, t: Token) -> State<Parser, Token> {
+        let (name, position) = self.let_name.take().unwrap();
+        self.parse_stack
+            .push(Incomplete::LetValue(name, position, Vec::new()));
+        match t.token_type {
+            TokenType::Equals => State(Self::normal),
+            _ => {
+                self.errors.push(ParseError::MissingEquals(t.position));
+                self.normal(t)
+            }
+        }
+    }
+
+    /// If the dot is missing the parser pretends that it was there and the token starts the
+    /// body of the lambda.
     fn expect_dot_or_identifier(&mut self, t: Token) -> State<Parser, Token> {
         match t.token_type {
             TokenType::Dot => State(Self::normal),
             TokenType::Identifier(s) => {
-                self.parse_stack.push(Incomplete::Lambda(s, Vec::new()));
+                self.parse_stack
+                    .push(Incomplete::Lambda(s, t.position, Vec::new()));
+                State(Self::expect_dot_or_identifier)
+            }
+            TokenType::Number(n) => {
+                self.errors
+                    .push(ParseError::MissingIdentifierAfterLambda(t.position));
+                self.parse_stack
+                    .push(Incomplete::Lambda(n.to_string(), t.position, Vec::new()));
                 State(Self::expect_dot_or_identifier)
             }
             _ => {
-                println!("a dot or an identifier was expected after a lambda and an identifier");
-                println!("found {:?} instead.", t);
-                panic!();
+                self.errors.push(ParseError::MissingDot(t.position));
+                self.normal(t)
             }
         }
     }
 }
 
 #[cfg(test)]
-mod test {
-    use super::{Expression, Parser};
-    use lexer::Lexer;
+pub mod test {
+    use super::{Expression, ParseError, Parser, Program};
+    use encoding::NumeralEncoding;
+    use lexer::{LexError, Lexer, Point, Position, Token};
+    use span::SpanTree;
     use std::collections::VecDeque;
 
-    fn lex_parse_and_assert(string_slice: &str, expected: Expression) {
+    fn lex(string_slice: &str) -> VecDeque<Result<Token, LexError>> {
         let mut tokens = VecDeque::new();
-        {
-            let mut lexer = Lexer::new(&mut tokens);
-            lexer.run(string_slice.to_string());
-        }
-        let mut parser = Parser::new();
-        let expression = parser.run(tokens);
+        Lexer::new(&mut tokens).run(string_slice.to_string());
+        tokens
+    }
+
+    /// Lexes and parses `string_slice`, which has to be an expression without errors. The
+    /// tests of the other modules start from source text with this.
+    pub fn lex_and_parse(string_slice: &str) -> Expression {
+        try_lex_and_parse(string_slice).unwrap()
+    }
+
+    /// Like `lex_and_parse`, but for a whole program.
+    pub fn lex_and_parse_program(string_slice: &str) -> Program {
+        try_lex_and_parse_program(string_slice).unwrap()
+    }
+
+    fn try_lex_and_parse(string_slice: &str) -> Result<Expression, Vec<ParseError>> {
+        Parser::new().run(lex(string_slice))
+    }
+
+    fn try_lex_and_parse_program(string_slice: &str) -> Result<Program, Vec<ParseError>> {
+        Parser::new().run_program(lex(string_slice))
+    }
+
+    fn lex_parse_and_assert(string_slice: &str, expected: Expression) {
+        let expression = try_lex_and_parse(string_slice).expect("parsing failed");
         assert_eq!(expression, expected);
     }
 
-    fn lex_and_parse_only(string_slice: &str) {
-        let mut tokens = VecDeque::new();
-        {
-            let mut lexer = Lexer::new(&mut tokens);
-            lexer.run(string_slice.to_string());
-        }
-        let mut parser = Parser::new();
-        let expression = parser.run(tokens);
+    fn lex_parse_and_assert_errors(string_slice: &str, expected: Vec<ParseError>) {
+        assert_eq!(try_lex_and_parse(string_slice), Err(expected));
+    }
+
+    fn position(from: (usize, usize), to: (usize, usize)) -> Position {
+        Position::new(Point::new(from.0, from.1), Point::new(to.0, to.1))
     }
 
     #[test]
@@ -252,33 +644,339 @@ mod test {
     }
 
     #[test]
-    #[should_panic]
     fn try_to_parse_unicode_lambda() {
-        lex_and_parse_only("λ");
+        lex_parse_and_assert_errors(
+            "λ",
+            vec![ParseError::MissingIdentifierAfterLambda(position(
+                (0, 0),
+                (0, 0),
+            ))],
+        );
     }
 
     #[test]
-    #[should_panic]
     fn try_to_parse_non_unicode_lambda() {
-        lex_and_parse_only("\\");
+        lex_parse_and_assert_errors(
+            "\\",
+            vec![ParseError::MissingIdentifierAfterLambda(position(
+                (0, 0),
+                (0, 0),
+            ))],
+        );
     }
 
     #[test]
-    #[should_panic]
     fn try_to_parse_dot() {
-        lex_and_parse_only(".");
+        lex_parse_and_assert_errors(
+            ".",
+            vec![ParseError::DotWithoutLambda(position((0, 0), (0, 0)))],
+        );
     }
 
     #[test]
-    #[should_panic]
     fn try_to_parse_left_parenthesis() {
-        lex_and_parse_only("(");
+        lex_parse_and_assert_errors(
+            "(",
+            vec![ParseError::UnclosedBracket(position((0, 0), (0, 0)))],
+        );
     }
 
     #[test]
-    #[should_panic]
     fn try_to_parse_right_parenthesis() {
-        lex_and_parse_only(")");
+        lex_parse_and_assert_errors(
+            ")",
+            vec![ParseError::UnexpectedClosingBracket(position(
+                (0, 0),
+                (0, 0),
+            ))],
+        );
+    }
+
+    #[test]
+    fn try_to_parse_empty_parenthesis() {
+        lex_parse_and_assert_errors(
+            "x ( )",
+            vec![ParseError::EmptyParenthesis(position((0, 2), (0, 4)))],
+        );
+    }
+
+    #[test]
+    fn try_to_parse_lambda_without_body() {
+        lex_parse_and_assert_errors(
+            "λx.",
+            vec![ParseError::EmptyLambdaBody(position((0, 0), (0, 1)))],
+        );
+    }
+
+    #[test]
+    fn try_to_parse_lambda_without_dot() {
+        lex_parse_and_assert_errors(
+            "λx(x)",
+            vec![ParseError::MissingDot(position((0, 2), (0, 2)))],
+        );
+    }
+
+    #[test]
+    fn report_every_error() {
+        lex_parse_and_assert_errors(
+            "(λ.x) ) λy(y) $ . (z",
+            vec![
+                ParseError::MissingIdentifierAfterLambda(position((0, 2), (0, 2))),
+                ParseError::UnexpectedClosingBracket(position((0, 6), (0, 6))),
+                ParseError::MissingDot(position((0, 10), (0, 10))),
+                ParseError::LexError(LexError::InvalidCharacterError(
+                    '$',
+                    position((0, 14), (0, 14)),
+                )),
+                ParseError::DotWithoutLambda(position((0, 16), (0, 16))),
+                ParseError::UnclosedBracket(position((0, 18), (0, 18))),
+            ],
+        );
+    }
+
+    #[test]
+    fn try_to_parse_nothing() {
+        lex_parse_and_assert_errors("  ", vec![ParseError::EmptyExpression]);
+    }
+
+    #[test]
+    fn try_to_parse_invalid_characters() {
+        lex_parse_and_assert_errors(
+            "x $ y %",
+            vec![
+                ParseError::LexError(LexError::InvalidCharacterError(
+                    '$',
+                    position((0, 2), (0, 2)),
+                )),
+                ParseError::LexError(LexError::InvalidCharacterError(
+                    '%',
+                    position((0, 6), (0, 6)),
+                )),
+            ],
+        );
+    }
+
+    #[test]
+    fn parse_spans() {
+        let (_, spans) = Parser::new().run_with_spans(lex("(λx y.x) (z)")).unwrap();
+        let leaf = |from, to| SpanTree::new(Some(position(from, to)), Vec::new());
+        let expected = SpanTree::new(
+            Some(position((0, 1), (0, 10))),
+            vec![
+                SpanTree::new(
+                    Some(position((0, 1), (0, 6))),
+                    vec![SpanTree::new(
+                        Some(position((0, 4), (0, 6))),
+                        vec![leaf((0, 6), (0, 6))],
+                    )],
+                ),
+                leaf((0, 10), (0, 10)),
+            ],
+        );
+        assert_eq!(spans, expected);
+    }
+
+    #[test]
+    fn parse_program() {
+        let program =
+            try_lex_and_parse_program("TRUE = λt f.t;\nFALSE = λt f.f;\nTRUE FALSE").unwrap();
+        let definitions: Vec<_> = program
+            .definitions
+            .iter()
+            .map(|d| (d.name.as_str(), d.position))
+            .collect();
+        assert_eq!(
+            definitions,
+            vec![
+                ("TRUE", position((0, 0), (0, 3))),
+                ("FALSE", position((1, 0), (1, 4))),
+            ]
+        );
+        let (main, _) = program.main.unwrap();
+        assert_eq!(
+            main,
+            Expression::Application(vec![
+                Expression::Variable("TRUE".into()),
+                Expression::Variable("FALSE".into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_program_without_main_expression() {
+        let program = try_lex_and_parse_program("I = λx.x;").unwrap();
+        assert_eq!(program.definitions.len(), 1);
+        assert_eq!(program.main, None);
+    }
+
+    #[test]
+    fn parse_main_expression_ending_in_semicolon() {
+        let program = try_lex_and_parse_program("I = λx.x; I y;").unwrap();
+        assert_eq!(program.definitions.len(), 1);
+        let (main, _) = program.main.unwrap();
+        assert_eq!(
+            main,
+            Expression::Application(vec![
+                Expression::Variable("I".into()),
+                Expression::Variable("y".into()),
+            ])
+        );
+        let program = try_lex_and_parse_program("x;").unwrap();
+        assert_eq!(program.main.unwrap().0, Expression::Variable("x".into()));
+    }
+
+    #[test]
+    fn try_to_parse_misplaced_statements() {
+        let errors = try_lex_and_parse_program("x y = z; w; v").unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                ParseError::UnexpectedEquals(position((0, 4), (0, 4))),
+                ParseError::MisplacedExpression(position((0, 0), (0, 6))),
+                ParseError::MisplacedExpression(position((0, 9), (0, 9))),
+            ]
+        );
+    }
+
+    #[test]
+    fn try_to_parse_empty_definition() {
+        let errors = try_lex_and_parse_program("I = ; (x").unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                ParseError::EmptyDefinition(position((0, 0), (0, 0))),
+                ParseError::UnclosedBracket(position((0, 6), (0, 6))),
+            ]
+        );
+    }
+
+    #[test]
+    fn try_to_parse_definition_as_expression() {
+        lex_parse_and_assert_errors(
+            "I = λx.x; I",
+            vec![ParseError::UnexpectedDefinition(position((0, 0), (0, 0)))],
+        );
+    }
+
+    #[test]
+    fn parse_with_comment_tokens() {
+        let mut tokens = VecDeque::new();
+        {
+            let mut lexer = Lexer::new(&mut tokens).keep_comments(true);
+            lexer.run("λ{- binder -}x.x -- identity".to_string());
+        }
+        let expected = Expression::Lambda("x".into(), Box::new(Expression::Variable("x".into())));
+        assert_eq!(Parser::new().run(tokens), Ok(expected));
+    }
+
+    fn lex_and_parse_with_encoding(
+        string_slice: &str,
+        numeral_encoding: NumeralEncoding,
+    ) -> Expression {
+        let mut parser = Parser::with_encoding(numeral_encoding);
+        parser.run(lex(string_slice)).unwrap()
+    }
+
+    #[test]
+    fn parse_church_numerals() {
+        let three = lex_and_parse_with_encoding("3", NumeralEncoding::Church);
+        let expected = lex_and_parse_with_encoding("λf x.f (f (f x))", NumeralEncoding::Church);
+        assert_eq!(three, expected);
+        let zero = lex_and_parse_with_encoding("0", NumeralEncoding::Church);
+        let expected = lex_and_parse_with_encoding("λf x.x", NumeralEncoding::Church);
+        assert_eq!(zero, expected);
+    }
+
+    #[test]
+    fn parse_scott_numerals() {
+        let two = lex_and_parse_with_encoding("x 2", NumeralEncoding::Scott);
+        let expected =
+            lex_and_parse_with_encoding("x (λs z.s (λs z.s (λs z.z)))", NumeralEncoding::Scott);
+        assert_eq!(two, expected);
+    }
+
+    #[test]
+    fn parse_parigot_numerals() {
+        let two = lex_and_parse_with_encoding("2", NumeralEncoding::Parigot);
+        let expected = lex_and_parse_with_encoding(
+            "λs z.s (λs z.s (λs z.z) ((λs z.z) s z)) ((λs z.s (λs z.z) ((λs z.z) s z)) s z)",
+            NumeralEncoding::Parigot,
+        );
+        assert_eq!(two, expected);
+    }
+
+    #[test]
+    fn parse_too_large_parigot_numeral() {
+        let mut parser = Parser::with_encoding(NumeralEncoding::Parigot);
+        assert_eq!(
+            parser.run(lex("x 17")),
+            Err(vec![ParseError::LexError(LexError::IdentifierError(
+                "The number 17 is too large".into(),
+                position((0, 2), (0, 3)),
+            ))])
+        );
+    }
+
+    #[test]
+    fn try_to_bind_a_number() {
+        lex_parse_and_assert_errors(
+            "λ1 x 2.x",
+            vec![
+                ParseError::MissingIdentifierAfterLambda(position((0, 1), (0, 1))),
+                ParseError::MissingIdentifierAfterLambda(position((0, 5), (0, 5))),
+            ],
+        );
+    }
+
+    #[test]
+    fn parse_let() {
+        let expected = Expression::Application(vec![
+            Expression::Lambda(
+                "x".into(),
+                Box::new(Expression::Application(vec![
+                    Expression::Variable("x".into()),
+                    Expression::Variable("x".into()),
+                ])),
+            ),
+            Expression::Lambda("y".into(), Box::new(Expression::Variable("y".into()))),
+        ]);
+        lex_parse_and_assert("let x = λy.y in x x", expected);
+    }
+
+    #[test]
+    fn parse_nested_let() {
+        let expected = Expression::Application(vec![
+            Expression::Variable("f".into()),
+            Expression::Application(vec![
+                Expression::Lambda(
+                    "a".into(),
+                    Box::new(Expression::Application(vec![
+                        Expression::Lambda("b".into(), Box::new(Expression::Variable("b".into()))),
+                        Expression::Variable("a".into()),
+                    ])),
+                ),
+                Expression::Application(vec![
+                    Expression::Lambda("c".into(), Box::new(Expression::Variable("c".into()))),
+                    Expression::Variable("z".into()),
+                ]),
+            ]),
+            Expression::Variable("w".into()),
+        ]);
+        lex_parse_and_assert("f (let a = let c = z in c in let b = a in b) w", expected);
+    }
+
+    #[test]
+    fn try_to_parse_broken_lets() {
+        lex_parse_and_assert_errors(
+            "(let x = y) (let = z in z) (let w y in y) in",
+            vec![
+                ParseError::MissingIn(position((0, 1), (0, 5))),
+                ParseError::MissingIdentifierAfterLet(position((0, 17), (0, 17))),
+                ParseError::UnexpectedIn(position((0, 21), (0, 22))),
+                ParseError::MissingEquals(position((0, 34), (0, 34))),
+                ParseError::UnexpectedIn(position((0, 42), (0, 43))),
+            ],
+        );
     }
 
     #[test]
@@ -0,0 +1,335 @@
+use parser::Expression;
+use std::fmt;
+use std::str::FromStr;
+
+/// How `Printer` writes lambdas.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum LambdaStyle {
+    /// `λx.x`
+    #[default]
+    Lambda,
+    /// `\x.x`, for when `λ` is hard to type or display.
+    Backslash,
+}
+
+/// Prints expressions in the notation the parser reads, with as few parentheses as possible.
+/// Applications associate to the left and lambda bodies extend as far right as they can, and
+/// nested lambdas are merged into `λx y.M`.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Printer {
+    lambda_style: LambdaStyle,
+    lets: bool,
+}
+
+impl Printer {
+    pub fn new() -> Self {
+        Printer::default()
+    }
+
+    pub fn lambda_style(mut self, lambda_style: LambdaStyle) -> Self {
+        self.lambda_style = lambda_style;
+        self
+    }
+
+    /// Whether to print `(λx.N) M` as `let x = M in N`, the way the parser desugars it.
+    /// Terms do not record whether they were written with `let`, so this is an approximation:
+    /// every such application is printed as a `let`, also the ones written as `(λx.N) M`.
+    pub fn lets(mut self, lets: bool) -> Self {
+        self.lets = lets;
+        self
+    }
+
+    pub fn print(&self, expr: &Expression) -> String {
+        let mut string = String::new();
+        self.print_into(&mut string, expr, true);
+        string
+    }
+
+    /// `rightmost` tells whether nothing follows `expr` that a lambda body would take in.
+    fn print_into(&self, string: &mut String, expr: &Expression, rightmost: bool) {
+        match expr {
+            Expression::Variable(i) => string.push_str(i),
+            Expression::Lambda(_, _) => {
+                self.in_parentheses_unless(rightmost, string, |string| {
+                    self.print_lambda(string, expr)
+                });
+            }
+            Expression::Application(v) => {
+                if let Some((name, value, body)) = self.as_let(expr) {
+                    self.in_parentheses_unless(rightmost, string, |string| {
+                        string.push_str("let ");
+                        string.push_str(name);
+                        string.push_str(" = ");
+                        self.print_into(string, value, true);
+                        string.push_str(" in ");
+                        self.print_into(string, body, true);
+                    });
+                    return;
+                }
+                let mut elements = Vec::new();
+                self.flatten_into(&mut elements, v);
+                for (i, e) in elements.iter().enumerate() {
+                    if i > 0 {
+                        string.push(' ');
+                    }
+                    let last = i == elements.len() - 1;
+                    match e {
+                        Expression::Application(_) if self.as_let(e).is_none() => {
+                            self.in_parentheses_unless(false, string, |string| {
+                                self.print_into(string, e, true)
+                            });
+                        }
+                        _ => self.print_into(string, e, rightmost && last),
+                    }
+                }
+            }
+        }
+    }
+
+    fn print_lambda(&self, string: &mut String, expr: &Expression) {
+        string.push_str(match self.lambda_style {
+            LambdaStyle::Lambda => "λ",
+            LambdaStyle::Backslash => "\\",
+        });
+        let mut body = expr;
+        let mut first = true;
+        while let Expression::Lambda(i, e) = body {
+            if !first {
+                string.push(' ');
+            }
+            string.push_str(i);
+            first = false;
+            body = e;
+        }
+        string.push('.');
+        self.print_into(string, body, true);
+    }
+
+    /// Collects the elements of `v`, taking applications in function position apart since
+    /// application associates to the left.
+    fn flatten_into<'a>(&self, elements: &mut Vec<&'a Expression>, v: &'a [Expression]) {
+        for (i, e) in v.iter().enumerate() {
+            match e {
+                Expression::Application(head) if i == 0 && self.as_let(e).is_none() => {
+                    self.flatten_into(elements, head)
+                }
+                _ => elements.push(e),
+            }
+        }
+    }
+
+    /// The name, value and body of `expr` if it is printed as a `let`.
+    fn as_let<'a>(
+        &self,
+        expr: &'a Expression,
+    ) -> Option<(&'a str, &'a Expression, &'a Expression)> {
+        match expr {
+            Expression::Application(v) if self.lets && v.len() == 2 => match &v[0] {
+                Expression::Lambda(name, body) => Some((name, &v[1], body)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn in_parentheses_unless<F>(&self, condition: bool, string: &mut String, print: F)
+    where
+        F: FnOnce(&mut String),
+    {
+        if condition {
+            print(string);
+        } else {
+            string.push('(');
+            print(string);
+            string.push(')');
+        }
+    }
+}
+
+/// How terms are shown in output.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    /// In the notation the parser reads, see `Printer`.
+    Pretty {
+        lambda_style: LambdaStyle,
+        lets: bool,
+    },
+    /// As the `Expression` tree itself, for looking into how a term was parsed.
+    Debug,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Format::Pretty {
+            lambda_style: LambdaStyle::default(),
+            lets: false,
+        }
+    }
+}
+
+impl Format {
+    pub fn show(self, expr: &Expression) -> String {
+        match self {
+            Format::Pretty { lambda_style, lets } => Printer::new()
+                .lambda_style(lambda_style)
+                .lets(lets)
+                .print(expr),
+            Format::Debug => format!("{:?}", expr),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "lambda" => Ok(Format::Pretty {
+                lambda_style: LambdaStyle::Lambda,
+                lets: false,
+            }),
+            "backslash" => Ok(Format::Pretty {
+                lambda_style: LambdaStyle::Backslash,
+                lets: false,
+            }),
+            "debug" => Ok(Format::Debug),
+            _ => Err(format!(
+                "unknown format `{}`, expected one of lambda, backslash and debug",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", Printer::new().print(self))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LambdaStyle, Printer};
+    use alpha::alpha_eq;
+    use parser::test::lex_and_parse;
+    use parser::Expression;
+
+    #[test]
+    fn print_with_few_parentheses() {
+        let cases = [
+            ("x", "x"),
+            ("(f a) b", "f a b"),
+            ("f (a b)", "f (a b)"),
+            ("λx.λy.x y", "λx y.x y"),
+            ("(λx.x) y", "(λx.x) y"),
+            ("f (λx.x)", "f λx.x"),
+            ("f (λx.x) y", "f (λx.x) y"),
+            ("(f (λx.x)) y", "f (λx.x) y"),
+            ("λx.(λy.y) x", "λx.(λy.y) x"),
+            ("λx.x (λy.y)", "λx.x λy.y"),
+            ("(λx.x (λy.y)) z", "(λx.x λy.y) z"),
+            ("f (g (λx.x)) a", "f (g λx.x) a"),
+            ("λf.f (λx.x) (λy.λz.z)", "λf.f (λx.x) λy z.z"),
+        ];
+        for &(term, expected) in &cases {
+            assert_eq!(lex_and_parse(term).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn print_with_backslashes() {
+        let printer = Printer::new().lambda_style(LambdaStyle::Backslash);
+        assert_eq!(
+            printer.print(&lex_and_parse("λx y.x (λz.z)")),
+            "\\x y.x \\z.z"
+        );
+    }
+
+    #[test]
+    fn print_lets() {
+        let printer = Printer::new().lets(true);
+        let cases = [
+            ("let x = a in x", "let x = a in x"),
+            ("let x = λy.y in x z", "let x = λy.y in x z"),
+            (
+                "let x = a in let y = b in x y",
+                "let x = a in let y = b in x y",
+            ),
+            ("let x = let y = a in y in x", "let x = let y = a in y in x"),
+            ("f (let x = a in x) b", "f (let x = a in x) b"),
+            ("(let x = a in x) b", "(let x = a in x) b"),
+            ("(λx y.x) a b", "(λx y.x) a b"),
+        ];
+        for &(term, expected) in &cases {
+            assert_eq!(printer.print(&lex_and_parse(term)), expected);
+        }
+        assert_eq!(lex_and_parse("let x = a in x").to_string(), "(λx.x) a");
+    }
+
+    /// A xorshift generator, so that the round trip test needs no dependencies and generates
+    /// the same expressions every time.
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    /// A random expression at most `depth` deep. Every application has at least two elements
+    /// and can start with an application, the way the parser builds `(f a) b`. The printer
+    /// leaves out the parentheses of those, so they only parse back up to alpha-equivalence.
+    fn random_expression(random: &mut Random, depth: usize) -> Expression {
+        const NAMES: [&str; 5] = ["x", "y", "z", "f", "x1"];
+        let name = NAMES[random.below(NAMES.len())].to_string();
+        if depth == 0 {
+            return Expression::Variable(name);
+        }
+        match random.below(3) {
+            0 => Expression::Variable(name),
+            1 => Expression::Lambda(name, Box::new(random_expression(random, depth - 1))),
+            _ => {
+                let head = match random.below(3) {
+                    0 => Expression::Variable(name),
+                    1 => Expression::Lambda(name, Box::new(random_expression(random, depth - 1))),
+                    _ => Expression::Application(vec![
+                        random_expression(random, depth - 1),
+                        random_expression(random, depth - 1),
+                    ]),
+                };
+                let mut v = vec![head];
+                for _ in 0..random.below(3) + 1 {
+                    v.push(random_expression(random, depth - 1));
+                }
+                Expression::Application(v)
+            }
+        }
+    }
+
+    #[test]
+    fn print_and_parse_back() {
+        let printers = [
+            Printer::new(),
+            Printer::new().lambda_style(LambdaStyle::Backslash),
+            Printer::new().lets(true),
+        ];
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+        for _ in 0..1000 {
+            let expression = random_expression(&mut random, 5);
+            for printer in &printers {
+                let printed = printer.print(&expression);
+                let parsed = lex_and_parse(&printed);
+                assert!(
+                    alpha_eq(&parsed, &expression),
+                    "{} was parsed as {:?} and not as {:?}",
+                    printed,
+                    parsed,
+                    expression
+                );
+            }
+        }
+    }
+}
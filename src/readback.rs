@@ -0,0 +1,217 @@
+use parser::Expression;
+use std::fmt;
+
+/// A normal form read back as the data it encodes.
+///
+/// Some encodings share terms: `λa b.b` is `0`, `false` and the empty Church list, and
+/// `λa b.a` is both `true` and the empty Scott list. Such terms are read back as the first
+/// of numeral, boolean, pair and list that matches, and empty lists only show up inside of
+/// lists.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Numeral(usize),
+    Boolean(bool),
+    Pair(Box<Value>, Box<Value>),
+    List(Vec<Value>),
+    /// Anything that is not recognised as data.
+    Term(Expression),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Numeral(n) => write!(fmt, "{}", n),
+            Value::Boolean(b) => write!(fmt, "{}", b),
+            Value::Pair(a, b) => write!(fmt, "({}, {})", a, b),
+            Value::List(v) => {
+                write!(fmt, "[")?;
+                for (i, value) in v.iter().enumerate() {
+                    if i > 0 {
+                        write!(fmt, ", ")?;
+                    }
+                    write!(fmt, "{}", value)?;
+                }
+                write!(fmt, "]")
+            }
+            Value::Term(e) => write!(fmt, "{}", e),
+        }
+    }
+}
+
+/// Recognises Church numerals, Church booleans, Church pairs and Church and Scott lists.
+/// Bound variables can have any names. The parts of pairs and lists are read back too.
+pub fn read_back(expr: &Expression) -> Value {
+    if let Some(n) = church_numeral(expr) {
+        Value::Numeral(n)
+    } else if let Some(b) = church_boolean(expr) {
+        Value::Boolean(b)
+    } else if let Some((a, b)) = church_pair(expr) {
+        Value::Pair(Box::new(read_back(a)), Box::new(read_back(b)))
+    } else if let Some(v) = church_list(expr).or_else(|| scott_list(expr)) {
+        Value::List(v.into_iter().map(read_back).collect())
+    } else {
+        Value::Term(expr.clone())
+    }
+}
+
+/// `λf x.f (f ... (f x))`
+fn church_numeral(expr: &Expression) -> Option<usize> {
+    let (f, x, mut body) = two_lambdas(expr)?;
+    let mut n = 0;
+    loop {
+        match body {
+            Expression::Variable(i) if i == x => return Some(n),
+            Expression::Application(v) if v.len() == 2 && f != x && is_variable(&v[0], f) => {
+                n += 1;
+                body = &v[1];
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// `λt f.t` and `λt f.f`
+fn church_boolean(expr: &Expression) -> Option<bool> {
+    let (t, f, body) = two_lambdas(expr)?;
+    match body {
+        Expression::Variable(i) if i == f => Some(false),
+        Expression::Variable(i) if i == t => Some(true),
+        _ => None,
+    }
+}
+
+/// `λs.s a b`
+fn church_pair(expr: &Expression) -> Option<(&Expression, &Expression)> {
+    match expr {
+        Expression::Lambda(s, body) => match &**body {
+            Expression::Application(v)
+                if v.len() == 3
+                    && is_variable(&v[0], s)
+                    && !occurs_free(s, &v[1])
+                    && !occurs_free(s, &v[2]) =>
+            {
+                Some((&v[1], &v[2]))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `λc n.c a (c b ... n)`
+fn church_list(expr: &Expression) -> Option<Vec<&Expression>> {
+    let (c, n, mut body) = two_lambdas(expr)?;
+    let mut elements = Vec::new();
+    loop {
+        match body {
+            Expression::Variable(i) if i == n => return Some(elements),
+            Expression::Application(v)
+                if v.len() == 3
+                    && c != n
+                    && is_variable(&v[0], c)
+                    && !occurs_free(c, &v[1])
+                    && !occurs_free(n, &v[1]) =>
+            {
+                elements.push(&v[1]);
+                body = &v[2];
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// `λn c.n` is the empty list and `λn c.c a l` puts `a` in front of the Scott list `l`.
+fn scott_list(expr: &Expression) -> Option<Vec<&Expression>> {
+    let mut elements = Vec::new();
+    let mut list = expr;
+    loop {
+        let (n, c, body) = two_lambdas(list)?;
+        match body {
+            Expression::Variable(i) if i == n && n != c => return Some(elements),
+            Expression::Application(v)
+                if v.len() == 3
+                    && is_variable(&v[0], c)
+                    && v[1..]
+                        .iter()
+                        .all(|e| !occurs_free(n, e) && !occurs_free(c, e)) =>
+            {
+                elements.push(&v[1]);
+                list = &v[2];
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Splits `λa b.M` into `a`, `b` and `M`.
+fn two_lambdas(expr: &Expression) -> Option<(&str, &str, &Expression)> {
+    match expr {
+        Expression::Lambda(a, e) => match &**e {
+            Expression::Lambda(b, body) => Some((a, b, body)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_variable(expr: &Expression, name: &str) -> bool {
+    match expr {
+        Expression::Variable(i) => i == name,
+        _ => false,
+    }
+}
+
+fn occurs_free(name: &str, expr: &Expression) -> bool {
+    match expr {
+        Expression::Variable(i) => i == name,
+        Expression::Lambda(i, e) => i != name && occurs_free(name, e),
+        Expression::Application(v) => v.iter().any(|e| occurs_free(name, e)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::read_back;
+    use parser::test::lex_and_parse;
+
+    fn lex_parse_and_read_back(string_slice: &str) -> String {
+        read_back(&lex_and_parse(string_slice)).to_string()
+    }
+
+    #[test]
+    fn read_back_numerals() {
+        assert_eq!(lex_parse_and_read_back("λf x.x"), "0");
+        assert_eq!(lex_parse_and_read_back("λs z.s (s (s z))"), "3");
+        assert_eq!(lex_parse_and_read_back("12"), "12");
+    }
+
+    #[test]
+    fn read_back_booleans() {
+        assert_eq!(lex_parse_and_read_back("λa b.a"), "true");
+        assert_eq!(lex_parse_and_read_back("λx x.x"), "0");
+    }
+
+    #[test]
+    fn read_back_pairs() {
+        assert_eq!(lex_parse_and_read_back("λp.p 1 (λa b.a)"), "(1, true)");
+        assert_eq!(lex_parse_and_read_back("λp.p (λq.q 0 1) 2"), "((0, 1), 2)");
+    }
+
+    #[test]
+    fn read_back_lists() {
+        assert_eq!(lex_parse_and_read_back("λc n.c 1 (c 2 n)"), "[1, 2]");
+        assert_eq!(
+            lex_parse_and_read_back("λn c.c 1 (λn c.c (λt f.t) (λn c.n))"),
+            "[1, true]"
+        );
+    }
+
+    #[test]
+    fn read_back_other_terms_as_they_are() {
+        assert_eq!(lex_parse_and_read_back("λf x.f x x"), "λf x.f x x");
+        assert_eq!(
+            lex_parse_and_read_back("λc n.c n (c 2 n)"),
+            "λc n.c n (c (λf x.f (f x)) n)"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use cli::parse_steps;
+use common::{Direction, Source};
+use diagnostics::Renderer;
+use interpreter::{expand_definitions, reduce, Options, Outcome};
+use lexer::{LexError, Lexer, TokenType};
+use parser::{Definition, ParseError, Parser, Program, Spanned};
+use readback::{read_back, Value};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use types::infer;
+
+const PROMPT: &str = "λ> ";
+/// Shown instead of `PROMPT` while brackets are left open.
+const CONTINUATION_PROMPT: &str = ".. ";
+
+pub const HELP: &str = "\
+Type a program to evaluate it. Its definitions are kept for the rest of the session.
+
+    :let NAME = expression    define NAME
+    :load FILE                run the program in FILE and keep its definitions
+    :strategy [NAME]          show or set the reduction strategy
+    :steps [N|none]           show or set the step limit
+    :type expression          show the simple type of an expression
+    :help                     show this message
+    :quit                     leave";
+
+/// What to do after an input.
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    /// Print this and wait for more input. Nothing is printed for an empty string.
+    Print(String),
+    Quit,
+}
+
+/// An interactive session that evaluates one input after another. The definitions of every
+/// input are added to the session so that later inputs can use them.
+pub struct Repl {
+    definitions: Vec<Definition>,
+    options: Options,
+    colour: bool,
+}
+
+impl Repl {
+    pub fn new(options: Options) -> Self {
+        Repl {
+            definitions: Vec::new(),
+            options,
+            colour: false,
+        }
+    }
+
+    /// Turns ANSI colours in error messages on or off. They are off by default.
+    pub fn colour(mut self, colour: bool) -> Self {
+        self.colour = colour;
+        self
+    }
+
+    /// Reads inputs from `input` and writes the replies to `output` until the input ends or
+    /// `:quit` is given. An input goes on over several lines while it has unclosed brackets.
+    pub fn run<R, W>(&mut self, input: R, mut output: W) -> io::Result<()>
+    where
+        R: BufRead,
+        W: Write,
+    {
+        let mut buffer = String::new();
+        write!(output, "{}", PROMPT)?;
+        output.flush()?;
+        for line in input.lines() {
+            buffer.push_str(&line?);
+            buffer.push('\n');
+            if is_incomplete(&buffer) {
+                write!(output, "{}", CONTINUATION_PROMPT)?;
+                output.flush()?;
+                continue;
+            }
+            match self.handle(&buffer) {
+                Reply::Print(ref text) if text.is_empty() => {}
+                Reply::Print(text) => writeln!(output, "{}", text)?,
+                Reply::Quit => return Ok(()),
+            }
+            buffer.clear();
+            write!(output, "{}", PROMPT)?;
+            output.flush()?;
+        }
+        writeln!(output)
+    }
+
+    /// Handles a single complete input, a program or a command starting with `:`.
+    pub fn handle(&mut self, input: &str) -> Reply {
+        let trimmed = input.trim_start();
+        if !trimmed.starts_with(':') {
+            return Reply::Print(self.evaluate(input));
+        }
+        let command = trimmed.split_whitespace().next().unwrap_or("");
+        // The command is blanked out rather than cut off so that positions in error
+        // messages still point into the input as it was typed.
+        let start = input.len() - trimmed.len() + command.len();
+        let argument: String = input[..start]
+            .chars()
+            .map(|c| if c == '\n' { c } else { ' ' })
+            .chain(input[start..].chars())
+            .collect();
+        Reply::Print(match command {
+            ":quit" | ":q" => return Reply::Quit,
+            ":help" | ":h" => HELP.to_string(),
+            ":let" => self.define(&argument),
+            ":load" => self.load(argument.trim()),
+            ":strategy" => match argument.trim() {
+                "" => self.options.strategy.to_string(),
+                name => match name.parse() {
+                    Ok(strategy) => {
+                        self.options.strategy = strategy;
+                        String::new()
+                    }
+                    Err(message) => message,
+                },
+            },
+            ":steps" => match argument.trim() {
+                "" => match self.options.max_steps {
+                    Some(max_steps) => max_steps.to_string(),
+                    None => "none".to_string(),
+                },
+                steps => match parse_steps(steps) {
+                    Ok(max_steps) => {
+                        self.options.max_steps = max_steps;
+                        String::new()
+                    }
+                    Err(message) => message,
+                },
+            },
+            ":type" => self.type_of(&argument),
+            _ => format!("unknown command `{}`, see :help", command),
+        })
+    }
+
+    fn evaluate(&mut self, source: &str) -> String {
+        match parse(source_chars(source)) {
+            Ok(program) => self.run_program(program),
+            Err(errors) => self.render(&errors, "<repl>", source),
+        }
+    }
+
+    /// Adds the definitions of `program` to the session and evaluates its main expression if
+    /// it has one.
+    fn run_program(&mut self, program: Program) -> String {
+        self.definitions.extend(program.definitions);
+        let (expression, spans) = match self.expand(program.main) {
+            Some(expanded) => expanded,
+            None => return String::new(),
+        };
+        let format = self.options.format;
+        match reduce(expression, spans, &self.options, |_, _, _| {}) {
+            Outcome::NormalForm(e, _) => match read_back(&e) {
+                Value::Term(_) => format.show(&e),
+                value => format!("{} -- {}", format.show(&e), value),
+            },
+            outcome => outcome.describe(format),
+        }
+    }
+
+    fn define(&mut self, source: &str) -> String {
+        let statement = format!("{};", source);
+        match parse(source_chars(&statement)) {
+            Ok(Program {
+                mut definitions,
+                main: None,
+            }) if definitions.len() == 1 => {
+                self.definitions.append(&mut definitions);
+                String::new()
+            }
+            Ok(_) => "`:let` takes a single `NAME = expression`".to_string(),
+            Err(errors) => self.render(&errors, "<repl>", &statement),
+        }
+    }
+
+    fn load(&mut self, path: &str) -> String {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => return format!("could not read {}: {}", path, err),
+        };
+        match parse(source_chars(&source)) {
+            Ok(program) => self.run_program(program),
+            Err(errors) => self.render(&errors, path, &source),
+        }
+    }
+
+    fn type_of(&mut self, source: &str) -> String {
+        let program = match parse(source_chars(source)) {
+            Ok(program) => program,
+            Err(errors) => return self.render(&errors, "<repl>", source),
+        };
+        if !program.definitions.is_empty() {
+            return "`:type` takes a single expression".to_string();
+        }
+        match self.expand(program.main) {
+            Some((expression, _)) => match infer(&expression) {
+                Ok(ty) => ty.to_string(),
+                Err(err) => err.to_string(),
+            },
+            None => "`:type` needs an expression".to_string(),
+        }
+    }
+
+    /// `main` with the definitions of the session put in.
+    fn expand(&self, main: Option<Spanned>) -> Option<Spanned> {
+        expand_definitions(Program {
+            definitions: self.definitions.clone(),
+            main,
+        })
+    }
+
+    fn render(&self, errors: &[ParseError], file_name: &str, source: &str) -> String {
+        let renderer = Renderer::new(file_name, source).colour(self.colour);
+        let rendered: Vec<String> = errors.iter().map(|e| renderer.render(e)).collect();
+        rendered.join("\n").trim_end().to_string()
+    }
+}
+
+fn source_chars(source: &str) -> VecDeque<char> {
+    source.chars().collect()
+}
+
+fn parse<I: Source<char>>(char_source: I) -> Result<Program, Vec<ParseError>> {
+    let mut tokens = VecDeque::new();
+    Lexer::new(&mut tokens).run(char_source);
+    Parser::new().run_program(tokens)
+}
+
+/// Whether `input` has brackets or a block comment that are still open, so that the rest of
+/// it is on the next lines.
+fn is_incomplete(input: &str) -> bool {
+    let mut tokens = VecDeque::new();
+    Lexer::new(&mut tokens).run(source_chars(input));
+    let mut depth = 0isize;
+    for token in tokens {
+        match token {
+            Ok(token) => match token.token_type {
+                TokenType::Bracket(Direction::Left) => depth += 1,
+                TokenType::Bracket(Direction::Right) => depth -= 1,
+                _ => {}
+            },
+            Err(LexError::UnterminatedCommentError(_)) => return true,
+            Err(_) => {}
+        }
+    }
+    depth > 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Repl, Reply};
+    use interpreter::Options;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    fn printed(repl: &mut Repl, input: &str) -> String {
+        match repl.handle(input) {
+            Reply::Print(text) => text,
+            Reply::Quit => panic!("{} quit the session", input),
+        }
+    }
+
+    #[test]
+    fn keep_definitions() {
+        let mut repl = Repl::new(Options::default());
+        assert_eq!(printed(&mut repl, ":let I = λx.x"), "");
+        assert_eq!(printed(&mut repl, "K = λx y.x;"), "");
+        assert_eq!(printed(&mut repl, "K I a b"), "b");
+        assert_eq!(printed(&mut repl, "λf x.f (f x)"), "λf x.f (f x) -- 2");
+        assert_eq!(
+            printed(&mut repl, ":let I"),
+            "`:let` takes a single `NAME = expression`"
+        );
+        assert!(printed(&mut repl, ":let I = (").starts_with("error: "));
+        assert_eq!(repl.handle(":quit"), Reply::Quit);
+    }
+
+    #[test]
+    fn change_options() {
+        let mut repl = Repl::new(Options::default());
+        assert_eq!(printed(&mut repl, ":strategy cbn"), "");
+        assert_eq!(printed(&mut repl, ":strategy"), "cbn");
+        assert_eq!(printed(&mut repl, "λx.(λy.y) x"), "λx.(λy.y) x");
+        assert!(printed(&mut repl, ":strategy lazy").starts_with("unknown strategy"));
+        assert_eq!(printed(&mut repl, ":steps 5"), "");
+        assert_eq!(printed(&mut repl, ":steps"), "5");
+        assert_eq!(
+            printed(&mut repl, "(λx.x x) (λx.x x)"),
+            "no normal form after 5 steps, giving up"
+        );
+    }
+
+    #[test]
+    fn show_types() {
+        let mut repl = Repl::new(Options::default());
+        printed(&mut repl, "S = λx y z.x z (y z);");
+        assert_eq!(
+            printed(&mut repl, ":type S"),
+            "(a → b → c) → (a → b) → a → c"
+        );
+        assert!(printed(&mut repl, ":type λx.x x").contains("infinite type"));
+    }
+
+    #[test]
+    fn load_files() {
+        let path = env::temp_dir().join(format!("lamppa-repl-test-{}.lc", process::id()));
+        fs::write(&path, "TRUE = λt f.t;\nFALSE = λt f.f;\nFALSE FALSE TRUE").unwrap();
+        let mut repl = Repl::new(Options::default());
+        let load = format!(":load {}", path.display());
+        assert_eq!(printed(&mut repl, &load), "λt f.t -- true");
+        assert_eq!(printed(&mut repl, "FALSE TRUE FALSE"), "λt f.f -- 0");
+        fs::write(&path, b"\xff\xfe").unwrap();
+        assert!(printed(&mut repl, &load).starts_with("could not read"));
+        fs::remove_file(&path).unwrap();
+        assert!(printed(&mut repl, &load).starts_with("could not read"));
+        let directory = format!(":load {}", env::temp_dir().display());
+        assert!(printed(&mut repl, &directory).starts_with("could not read"));
+    }
+
+    #[test]
+    fn read_lines_until_brackets_are_closed() {
+        let mut output = Vec::new();
+        let input = "(λx.\n  x) y\n\n:quit\nz\n";
+        Repl::new(Options::default())
+            .run(input.as_bytes(), &mut output)
+            .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "λ> .. y\nλ> λ> ");
+    }
+}
@@ -0,0 +1,138 @@
+use lexer::Position;
+use parser::Expression;
+
+/// Source positions of an `Expression`, kept in a tree of the same shape as the expression.
+/// A `Variable` has no children, a `Lambda` has one for its body and an `Application` has one
+/// for every expression in it. Nodes that did not come from the source have no position.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpanTree {
+    pub position: Option<Position>,
+    pub children: Vec<SpanTree>,
+}
+
+/// The position that starts where `from` starts and ends where `to` ends, if both are known.
+pub fn span_between(from: Option<Position>, to: Option<Position>) -> Option<Position> {
+    match (from, to) {
+        (Some(from), Some(to)) => Some(Position::new(from.starting_point, to.ending_point)),
+        _ => None,
+    }
+}
+
+/// Like `span_between`, but for the parts `from` and `to` of a node at `node`. Once terms are
+/// substituted, the parts can come from unrelated places in the source, like a definition and
+/// the expression that uses it, and the position between them would cover the text in
+/// between as well. So they are only joined when both lie within `node`, and `node` is given
+/// otherwise.
+pub fn span_inside(
+    node: Option<Position>,
+    from: Option<Position>,
+    to: Option<Position>,
+) -> Option<Position> {
+    match (node, from, to) {
+        (Some(node), Some(from), Some(to)) if node.contains(from) && node.contains(to) => {
+            span_between(Some(from), Some(to))
+        }
+        _ => node,
+    }
+}
+
+impl SpanTree {
+    pub fn new(position: Option<Position>, children: Vec<SpanTree>) -> Self {
+        SpanTree { position, children }
+    }
+
+    /// A tree without any positions for an expression that was not parsed from a source.
+    pub fn unknown(expr: &Expression) -> Self {
+        SpanTree::filled(expr, None)
+    }
+
+    /// A tree where every part of `expr` has the same position, like the parts of a term
+    /// that a numeric literal stands for.
+    pub fn filled(expr: &Expression, position: Option<Position>) -> Self {
+        let children = match expr {
+            Expression::Variable(_) => Vec::new(),
+            Expression::Lambda(_, e) => vec![SpanTree::filled(e, position)],
+            Expression::Application(v) => v.iter().map(|e| SpanTree::filled(e, position)).collect(),
+        };
+        SpanTree::new(position, children)
+    }
+
+    /// Follows `path` down the tree, one child index at a time.
+    pub fn get(&self, path: &[usize]) -> Option<&SpanTree> {
+        match path.split_first() {
+            Some((&i, rest)) => self.children.get(i).and_then(|c| c.get(rest)),
+            None => Some(self),
+        }
+    }
+
+    /// Mirrors substituting `to` for the free occurrences of `from` in `body`, where `self`
+    /// holds the positions of `body` and `to_spans` the positions of `to`. Substituted
+    /// variables take the positions of the argument so that the result still points to the
+    /// source of every part of it.
+    pub fn substitute(&self, body: &Expression, from: &str, to_spans: &SpanTree) -> SpanTree {
+        match body {
+            Expression::Variable(i) => {
+                if i == from {
+                    to_spans.clone()
+                } else {
+                    self.clone()
+                }
+            }
+            Expression::Lambda(i, e) => {
+                if i == from {
+                    self.clone()
+                } else {
+                    SpanTree::new(
+                        self.position,
+                        vec![self.children[0].substitute(e, from, to_spans)],
+                    )
+                }
+            }
+            Expression::Application(v) => SpanTree::new(
+                self.position,
+                v.iter()
+                    .zip(self.children.iter())
+                    .map(|(e, s)| s.substitute(e, from, to_spans))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SpanTree;
+    use lexer::{Point, Position};
+    use parser::Expression;
+
+    fn position(from: (usize, usize), to: (usize, usize)) -> Option<Position> {
+        Some(Position::new(
+            Point::new(from.0, from.1),
+            Point::new(to.0, to.1),
+        ))
+    }
+
+    #[test]
+    fn substitute_replaces_only_free_occurrences() {
+        let body = Expression::Application(vec![
+            Expression::Variable("x".into()),
+            Expression::Lambda("x".into(), Box::new(Expression::Variable("x".into()))),
+        ]);
+        let body_spans = SpanTree::new(
+            position((0, 0), (0, 6)),
+            vec![
+                SpanTree::new(position((0, 0), (0, 0)), Vec::new()),
+                SpanTree::new(
+                    position((0, 2), (0, 6)),
+                    vec![SpanTree::new(position((0, 6), (0, 6)), Vec::new())],
+                ),
+            ],
+        );
+        let argument_spans = SpanTree::new(position((1, 0), (1, 3)), Vec::new());
+        let expected = SpanTree::new(
+            position((0, 0), (0, 6)),
+            vec![argument_spans.clone(), body_spans.children[1].clone()],
+        );
+        assert_eq!(body_spans.substitute(&body, "x", &argument_spans), expected);
+    }
+}
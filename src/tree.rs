@@ -0,0 +1,270 @@
+use graph::escape;
+use parser::Expression;
+use pretty::LambdaStyle;
+use std::str::FromStr;
+
+/// The diagram languages `TreeWriter` can write.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Diagram {
+    /// The DOT language of Graphviz.
+    #[default]
+    Dot,
+    Mermaid,
+}
+
+impl FromStr for Diagram {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "dot" => Ok(Diagram::Dot),
+            "mermaid" => Ok(Diagram::Mermaid),
+            _ => Err(format!(
+                "unknown diagram `{}`, expected dot or mermaid",
+                name
+            )),
+        }
+    }
+}
+
+/// Draws the syntax tree of an expression. Lambdas are nodes like `λx` with the body below
+/// them, applications are `@` nodes with the function and its arguments below them, and
+/// variables are leaves.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct TreeWriter {
+    diagram: Diagram,
+    lambda_style: LambdaStyle,
+    binary: bool,
+    binders: bool,
+}
+
+/// A node of the tree and the nodes below it, numbered in the order they are drawn.
+struct Node {
+    label: String,
+    children: Vec<usize>,
+    /// The lambda that binds a variable.
+    binder: Option<usize>,
+}
+
+impl TreeWriter {
+    pub fn new(diagram: Diagram) -> Self {
+        TreeWriter {
+            diagram,
+            ..TreeWriter::default()
+        }
+    }
+
+    pub fn lambda_style(mut self, lambda_style: LambdaStyle) -> Self {
+        self.lambda_style = lambda_style;
+        self
+    }
+
+    /// Whether to draw `f a b` curried as `(f a) b`, with two children below every `@`.
+    pub fn binary(mut self, binary: bool) -> Self {
+        self.binary = binary;
+        self
+    }
+
+    /// Whether to draw a dashed edge from every bound variable to the lambda that binds it.
+    pub fn binders(mut self, binders: bool) -> Self {
+        self.binders = binders;
+        self
+    }
+
+    pub fn write(&self, expr: &Expression) -> String {
+        let mut nodes = Vec::new();
+        self.add(&mut nodes, expr, &mut Vec::new());
+        match self.diagram {
+            Diagram::Dot => self.write_dot(&nodes),
+            Diagram::Mermaid => self.write_mermaid(&nodes),
+        }
+    }
+
+    /// Adds the nodes of `expr` and returns the number of its root. `binders` are the names
+    /// and nodes of the lambdas around `expr`, the innermost last.
+    fn add<'a>(
+        &self,
+        nodes: &mut Vec<Node>,
+        expr: &'a Expression,
+        binders: &mut Vec<(&'a str, usize)>,
+    ) -> usize {
+        let node = nodes.len();
+        match expr {
+            Expression::Variable(i) => {
+                let binder = binders.iter().rev().find(|(name, _)| name == i);
+                nodes.push(Node {
+                    label: i.clone(),
+                    children: Vec::new(),
+                    binder: binder.map(|&(_, node)| node),
+                });
+            }
+            Expression::Lambda(i, e) => {
+                let lambda = match self.lambda_style {
+                    LambdaStyle::Lambda => "λ",
+                    LambdaStyle::Backslash => "\\",
+                };
+                nodes.push(Node {
+                    label: format!("{}{}", lambda, i),
+                    children: Vec::new(),
+                    binder: None,
+                });
+                binders.push((i, node));
+                let body = self.add(nodes, e, binders);
+                binders.pop();
+                nodes[node].children.push(body);
+            }
+            Expression::Application(v) => return self.add_application(nodes, v, binders),
+        }
+        node
+    }
+
+    fn add_application<'a>(
+        &self,
+        nodes: &mut Vec<Node>,
+        v: &'a [Expression],
+        binders: &mut Vec<(&'a str, usize)>,
+    ) -> usize {
+        let node = nodes.len();
+        nodes.push(Node {
+            label: "@".to_string(),
+            children: Vec::new(),
+            binder: None,
+        });
+        if self.binary && v.len() > 2 {
+            let (last, function) = v.split_last().unwrap();
+            let function = self.add_application(nodes, function, binders);
+            let argument = self.add(nodes, last, binders);
+            nodes[node].children = vec![function, argument];
+        } else {
+            for e in v {
+                let child = self.add(nodes, e, binders);
+                nodes[node].children.push(child);
+            }
+        }
+        node
+    }
+
+    fn write_dot(&self, nodes: &[Node]) -> String {
+        let mut dot = String::from("digraph syntax {\n");
+        for (i, node) in nodes.iter().enumerate() {
+            dot.push_str(&format!(
+                "    n{} [label=\"{}\"];\n",
+                i,
+                escape(&node.label)
+            ));
+        }
+        for (i, node) in nodes.iter().enumerate() {
+            for child in &node.children {
+                dot.push_str(&format!("    n{} -> n{};\n", i, child));
+            }
+        }
+        // Binder edges leave the layout alone so that the tree is still drawn as a tree.
+        for (i, binder) in self.binder_edges(nodes) {
+            dot.push_str(&format!(
+                "    n{} -> n{} [style=dashed, constraint=false];\n",
+                i, binder
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn write_mermaid(&self, nodes: &[Node]) -> String {
+        let mut mermaid = String::from("graph TD\n");
+        for (i, node) in nodes.iter().enumerate() {
+            mermaid.push_str(&format!(
+                "    n{}[\"{}\"]\n",
+                i,
+                node.label.replace('"', "#quot;")
+            ));
+        }
+        for (i, node) in nodes.iter().enumerate() {
+            for child in &node.children {
+                mermaid.push_str(&format!("    n{} --> n{}\n", i, child));
+            }
+        }
+        for (i, binder) in self.binder_edges(nodes) {
+            mermaid.push_str(&format!("    n{} -.-> n{}\n", i, binder));
+        }
+        mermaid
+    }
+
+    fn binder_edges(&self, nodes: &[Node]) -> Vec<(usize, usize)> {
+        if !self.binders {
+            return Vec::new();
+        }
+        nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, node)| node.binder.map(|binder| (i, binder)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Diagram, TreeWriter};
+    use parser::test::lex_and_parse;
+    use pretty::LambdaStyle;
+
+    #[test]
+    fn write_dot() {
+        let writer = TreeWriter::new(Diagram::Dot).binders(true);
+        let expected = "\
+digraph syntax {
+    n0 [label=\"λx\"];
+    n1 [label=\"@\"];
+    n2 [label=\"f\"];
+    n3 [label=\"x\"];
+    n4 [label=\"y\"];
+    n0 -> n1;
+    n1 -> n2;
+    n1 -> n3;
+    n1 -> n4;
+    n3 -> n0 [style=dashed, constraint=false];
+}
+";
+        assert_eq!(writer.write(&lex_and_parse("λx.f x y")), expected);
+        let writer = TreeWriter::new(Diagram::Dot).lambda_style(LambdaStyle::Backslash);
+        assert!(writer
+            .write(&lex_and_parse("λx.x"))
+            .contains("n0 [label=\"\\\\x\"];"));
+    }
+
+    #[test]
+    fn write_mermaid() {
+        let writer = TreeWriter::new(Diagram::Mermaid).binary(true).binders(true);
+        let expected = "\
+graph TD
+    n0[\"λx\"]
+    n1[\"λx\"]
+    n2[\"@\"]
+    n3[\"@\"]
+    n4[\"f\"]
+    n5[\"x\"]
+    n6[\"x\"]
+    n0 --> n1
+    n1 --> n2
+    n2 --> n3
+    n2 --> n6
+    n3 --> n4
+    n3 --> n5
+    n5 -.-> n1
+    n6 -.-> n1
+";
+        assert_eq!(writer.write(&lex_and_parse("λx x.f x x")), expected);
+    }
+
+    #[test]
+    fn curry_applications() {
+        let expression = lex_and_parse("f a b c");
+        let count = |writer: TreeWriter| writer.write(&expression).matches("\"@\"").count();
+        assert_eq!(count(TreeWriter::new(Diagram::Dot)), 1);
+        assert_eq!(count(TreeWriter::new(Diagram::Dot).binary(true)), 3);
+        let binary = TreeWriter::new(Diagram::Dot).binary(true);
+        assert_eq!(
+            binary.write(&expression),
+            binary.write(&lex_and_parse("((f a) b) c"))
+        );
+    }
+}
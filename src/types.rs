@@ -0,0 +1,225 @@
+use parser::Expression;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A simple type: a type variable or a function type. `infer` numbers the type variables
+/// from zero in the order they appear, and they are shown as `a`, `b` and so on.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Type {
+    Variable(usize),
+    Arrow(Box<Type>, Box<Type>),
+}
+
+/// Why a term has no simple type. Unification can only fail one way: a type variable would
+/// have to be a type that contains it, like the type of `x` in `λx.x x`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeError {
+    pub variable: Type,
+    pub infinite_type: Type,
+}
+
+/// The most general simple type of `expr`. Free variables can have any type, but every
+/// occurrence of one has the same type.
+pub fn infer(expr: &Expression) -> Result<Type, TypeError> {
+    let mut inference = Inference {
+        substitution: Vec::new(),
+        free: HashMap::new(),
+    };
+    let ty = inference.infer(expr, &mut Vec::new())?;
+    Ok(inference.resolve(&ty).renumber(&mut HashMap::new()))
+}
+
+struct Inference {
+    /// What each type variable has been unified with so far.
+    substitution: Vec<Option<Type>>,
+    free: HashMap<String, Type>,
+}
+
+impl Inference {
+    fn fresh(&mut self) -> Type {
+        self.substitution.push(None);
+        Type::Variable(self.substitution.len() - 1)
+    }
+
+    /// `binders` are the innermost lambdas around `expr` last, with the types of their
+    /// variables.
+    fn infer<'a>(
+        &mut self,
+        expr: &'a Expression,
+        binders: &mut Vec<(&'a str, Type)>,
+    ) -> Result<Type, TypeError> {
+        match expr {
+            Expression::Variable(i) => {
+                if let Some((_, ty)) = binders.iter().rev().find(|(name, _)| name == i) {
+                    return Ok(ty.clone());
+                }
+                if let Some(ty) = self.free.get(i) {
+                    return Ok(ty.clone());
+                }
+                let ty = self.fresh();
+                self.free.insert(i.clone(), ty.clone());
+                Ok(ty)
+            }
+            Expression::Lambda(i, e) => {
+                let argument = self.fresh();
+                binders.push((i, argument.clone()));
+                let body = self.infer(e, binders);
+                binders.pop();
+                Ok(Type::Arrow(Box::new(argument), Box::new(body?)))
+            }
+            Expression::Application(v) => {
+                let mut function = self.infer(&v[0], binders)?;
+                for e in &v[1..] {
+                    let argument = self.infer(e, binders)?;
+                    let result = self.fresh();
+                    let arrow = Type::Arrow(Box::new(argument), Box::new(result.clone()));
+                    self.unify(&function, &arrow)?;
+                    function = result;
+                }
+                Ok(function)
+            }
+        }
+    }
+
+    fn unify(&mut self, a: &Type, b: &Type) -> Result<(), TypeError> {
+        match (self.resolve(a), self.resolve(b)) {
+            (Type::Variable(x), Type::Variable(y)) if x == y => Ok(()),
+            (Type::Variable(x), ty) | (ty, Type::Variable(x)) => {
+                if ty.contains(x) {
+                    return Err(TypeError {
+                        variable: Type::Variable(x),
+                        infinite_type: ty,
+                    }
+                    .renumbered());
+                }
+                self.substitution[x] = Some(ty);
+                Ok(())
+            }
+            (Type::Arrow(a1, a2), Type::Arrow(b1, b2)) => {
+                self.unify(&a1, &b1)?;
+                self.unify(&a2, &b2)
+            }
+        }
+    }
+
+    /// `ty` with every type variable that has been unified with something replaced by it.
+    fn resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Variable(x) => match &self.substitution[*x] {
+                Some(ty) => self.resolve(ty),
+                None => ty.clone(),
+            },
+            Type::Arrow(a, b) => Type::Arrow(Box::new(self.resolve(a)), Box::new(self.resolve(b))),
+        }
+    }
+}
+
+impl Type {
+    fn contains(&self, x: usize) -> bool {
+        match self {
+            Type::Variable(y) => *y == x,
+            Type::Arrow(a, b) => a.contains(x) || b.contains(x),
+        }
+    }
+
+    /// Numbers the type variables in the order they appear, continuing from `numbers`.
+    fn renumber(&self, numbers: &mut HashMap<usize, usize>) -> Type {
+        match self {
+            Type::Variable(x) => {
+                let next = numbers.len();
+                Type::Variable(*numbers.entry(*x).or_insert(next))
+            }
+            Type::Arrow(a, b) => {
+                let a = a.renumber(numbers);
+                Type::Arrow(Box::new(a), Box::new(b.renumber(numbers)))
+            }
+        }
+    }
+
+    fn fmt_into(&self, fmt: &mut fmt::Formatter, left_of_arrow: bool) -> fmt::Result {
+        match self {
+            Type::Variable(x) => match x / 26 {
+                0 => write!(fmt, "{}", (b'a' + (x % 26) as u8) as char),
+                round => write!(fmt, "{}{}", (b'a' + (x % 26) as u8) as char, round),
+            },
+            Type::Arrow(a, b) => {
+                if left_of_arrow {
+                    write!(fmt, "(")?;
+                }
+                a.fmt_into(fmt, true)?;
+                write!(fmt, " → ")?;
+                b.fmt_into(fmt, false)?;
+                if left_of_arrow {
+                    write!(fmt, ")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Arrows associate to the right, so `a → b → a` is `a → (b → a)`.
+impl fmt::Display for Type {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_into(fmt, false)
+    }
+}
+
+impl TypeError {
+    fn renumbered(self) -> TypeError {
+        let mut numbers = HashMap::new();
+        TypeError {
+            variable: self.variable.renumber(&mut numbers),
+            infinite_type: self.infinite_type.renumber(&mut numbers),
+        }
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "the term has no simple type, {} would have to be the infinite type {}",
+            self.variable, self.infinite_type
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::infer;
+    use parser::test::lex_and_parse;
+
+    fn type_of(string_slice: &str) -> String {
+        match infer(&lex_and_parse(string_slice)) {
+            Ok(ty) => ty.to_string(),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn infer_types() {
+        let cases = [
+            ("λx.x", "a → a"),
+            ("λx y.x", "a → b → a"),
+            ("λx y z.x z (y z)", "(a → b → c) → (a → b) → a → c"),
+            ("2", "(a → a) → a → a"),
+            ("λf g x.f (g x)", "(a → b) → (c → a) → c → b"),
+            ("f x", "a"),
+            ("λx.f x x", "a → b"),
+            ("let id = λx.x in id", "a → a"),
+        ];
+        for &(term, expected) in &cases {
+            assert_eq!(type_of(term), expected, "{}", term);
+        }
+    }
+
+    #[test]
+    fn reject_terms_without_simple_types() {
+        assert_eq!(
+            type_of("λx.x x"),
+            "the term has no simple type, a would have to be the infinite type a → b"
+        );
+        assert!(infer(&lex_and_parse("λf.(λx.f (x x)) (λx.f (x x))")).is_err());
+    }
+}